@@ -0,0 +1,193 @@
+use std::f64::consts::TAU;
+use std::time::Duration;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use crate::game::Game;
+use crate::game_sprite::GameSprite;
+use crate::rect::Rect;
+use crate::rng::GameRng;
+use crate::steering::{center, towards};
+
+// how far from a black hole's middle orbiters circle
+const ORBIT_RADIUS: f64 = 120.0;
+// how fast orbiters go round, as a fraction of their top speed, leaving some to catch up with
+const ORBIT_SPEED: f64 = 0.75;
+// orbiters slow down when they're closer than this to their place on the orbit, and gunners
+// when they're this close to their range
+const ARRIVE_DISTANCE: f64 = 32.0;
+// how far from the player gunners like to keep
+const GUNNER_RANGE: f64 = 350.0;
+// gunners only take aim when the player is this much further than their range, at most
+const GUNNER_REACH: f64 = 1.5;
+// how long a wanderer keeps a heading, at most
+const WANDER_SECONDS: f64 = 2.0;
+
+fn sprite_center(sprite: &GameSprite) -> (f64, f64) {
+    (sprite.x + sprite.width / 2.0, sprite.y + sprite.height / 2.0)
+}
+
+/// Decides where an enemy wants to go. Gives the velocity it would like as a fraction of its
+/// top speed, `speed`, so at most one unit long; steering then decides how it actually moves.
+pub trait EnemyBehavior {
+    fn desired_velocity(&mut self, sprite: &GameSprite, game: &Game, rng: &mut GameRng, speed: f64, dt: f64) -> (f64, f64);
+
+    /// Where to fire a shot at this tick, if anywhere.
+    fn shoot(&mut self, _sprite: &GameSprite, _game: &Game, _now: Duration) -> Option<(f64, f64)> {
+        None
+    }
+
+    /// Where the next shot is going, while taking aim.
+    fn get_aim(&self) -> Option<(f64, f64)> {
+        None
+    }
+}
+
+/// Heads straight for the player.
+// braced, as TOML has no way to save a unit struct
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct Chaser {}
+
+impl EnemyBehavior for Chaser {
+    fn desired_velocity(&mut self, sprite: &GameSprite, game: &Game, _rng: &mut GameRng, _speed: f64, _dt: f64) -> (f64, f64) {
+        towards(sprite_center(sprite), center(&game.player))
+    }
+}
+
+/// Drifts about the playfield, turning now and then and bouncing off its edges.
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct Wanderer {
+    heading: f64,
+    turn_in: f64
+}
+
+impl EnemyBehavior for Wanderer {
+    fn desired_velocity(&mut self, sprite: &GameSprite, game: &Game, rng: &mut GameRng, _speed: f64, dt: f64) -> (f64, f64) {
+        self.turn_in -= dt;
+        if self.turn_in <= 0.0 {
+            self.heading = rng.gen_range(0.0, TAU);
+            self.turn_in = rng.gen_range(WANDER_SECONDS / 2.0, WANDER_SECONDS);
+        }
+        let (mut x, mut y) = (self.heading.cos(), self.heading.sin());
+        if (sprite.x <= 0.0 && x < 0.0) || (sprite.x + sprite.width >= game.screen_width && x > 0.0) {
+            x = -x;
+        }
+        if (sprite.y <= 0.0 && y < 0.0) || (sprite.y + sprite.height >= game.screen_height && y > 0.0) {
+            y = -y;
+        }
+        self.heading = y.atan2(x);
+        (x, y)
+    }
+}
+
+/// Circles the nearest open black hole, guarding it, and chases the player once none are left.
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct Orbiter {
+    angle: f64
+}
+
+impl Orbiter {
+    /// Starts circling `angle` radians round from the right of the black hole.
+    pub fn new(angle: f64) -> Self {
+        Self { angle }
+    }
+}
+
+impl EnemyBehavior for Orbiter {
+    fn desired_velocity(&mut self, sprite: &GameSprite, game: &Game, rng: &mut GameRng, speed: f64, dt: f64) -> (f64, f64) {
+        let (x, y) = sprite_center(sprite);
+        let distance = |r: &&Rect| {
+            let (cx, cy) = center(r);
+            (cx - x).powi(2) + (cy - y).powi(2)
+        };
+        let Some(black_hole) = game.black_holes.iter().min_by(|a, b| distance(a).total_cmp(&distance(b))) else {
+            return Chaser {}.desired_velocity(sprite, game, rng, speed, dt);
+        };
+
+        self.angle = (self.angle + ORBIT_SPEED * speed * dt / ORBIT_RADIUS).rem_euclid(TAU);
+        let (cx, cy) = center(black_hole);
+        let target = (cx + self.angle.cos() * ORBIT_RADIUS, cy + self.angle.sin() * ORBIT_RADIUS);
+        let (dx, dy) = towards((x, y), target);
+        // slows down as it catches up with its place on the orbit
+        let distance = (target.0 - x).hypot(target.1 - y);
+        let fraction = (distance / ARRIVE_DISTANCE).min(1.0);
+        (dx * fraction, dy * fraction)
+    }
+}
+
+/// Keeps its distance from the player and shoots. It stops to take aim first, giving time to
+/// get out of the way, and aims at the planet being towed if there is one, or else the player.
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct Gunner {
+    aim: Option<(f64, f64)>,
+    aim_start: Duration,
+    last_shot: Duration
+}
+
+impl EnemyBehavior for Gunner {
+    fn desired_velocity(&mut self, sprite: &GameSprite, game: &Game, _rng: &mut GameRng, _speed: f64, _dt: f64) -> (f64, f64) {
+        if self.aim.is_some() {
+            return (0.0, 0.0);
+        }
+        let (x, y) = sprite_center(sprite);
+        let player = center(&game.player);
+        let (dx, dy) = towards((x, y), player);
+        // backs off when the player gets inside its range
+        let fraction = (((player.0 - x).hypot(player.1 - y) - GUNNER_RANGE) / ARRIVE_DISTANCE).clamp(-1.0, 1.0);
+        (dx * fraction, dy * fraction)
+    }
+
+    fn shoot(&mut self, sprite: &GameSprite, game: &Game, now: Duration) -> Option<(f64, f64)> {
+        let tuning = &game.tuning;
+        match self.aim {
+            Some(target) if now.saturating_sub(self.aim_start) >= tuning.get_gunner_aim_time() => {
+                self.aim = None;
+                self.last_shot = now;
+                Some(target)
+            }
+            Some(_) => None,
+            None => {
+                let (x, y) = sprite_center(sprite);
+                let target = center(&game.towed_planet.unwrap_or(game.player));
+                let in_reach = (target.0 - x).hypot(target.1 - y) <= GUNNER_RANGE * GUNNER_REACH;
+                if in_reach && now.saturating_sub(self.last_shot) >= tuning.get_gunner_reload() {
+                    self.aim = Some(target);
+                    self.aim_start = now;
+                }
+                None
+            }
+        }
+    }
+
+    fn get_aim(&self) -> Option<(f64, f64)> {
+        self.aim
+    }
+}
+
+/// An enemy's behavior along with whatever it remembers, kept as an enum so it can be saved.
+#[derive(Clone, Deserialize, Serialize)]
+pub enum Behavior {
+    Chase(Chaser),
+    Wander(Wanderer),
+    Orbit(Orbiter),
+    Shoot(Gunner)
+}
+
+impl Behavior {
+    pub fn get(&self) -> &dyn EnemyBehavior {
+        match self {
+            Behavior::Chase(b) => b,
+            Behavior::Wander(b) => b,
+            Behavior::Orbit(b) => b,
+            Behavior::Shoot(b) => b
+        }
+    }
+
+    pub fn get_mut(&mut self) -> &mut dyn EnemyBehavior {
+        match self {
+            Behavior::Chase(b) => b,
+            Behavior::Wander(b) => b,
+            Behavior::Orbit(b) => b,
+            Behavior::Shoot(b) => b
+        }
+    }
+}
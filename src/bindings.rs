@@ -0,0 +1,181 @@
+use std::collections::BTreeMap;
+use piston::{Button, ControllerAxisEvent, Event, ReleaseEvent};
+use serde::{Deserialize, Serialize};
+use crate::controller::StickSettings;
+
+// how far a stick has to be pushed before the rebinding screen takes it
+const REBIND_AXIS_THRESHOLD: f64 = 0.75;
+
+/// Which physical axes and buttons of one controller drive which actions.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct GamepadBindings {
+    pub move_x_axis: u8,
+    pub move_y_axis: u8,
+    pub aim_x_axis: u8,
+    pub aim_y_axis: u8,
+    pub pause_button: u8,
+    /// Confirms and continues past messages. Any button without another job does too.
+    pub confirm_button: u8,
+    /// Opens the rebinding screen from the title screen.
+    pub rebind_button: u8,
+    /// Continues the saved game from the title screen.
+    pub continue_button: u8,
+    pub move_stick: StickSettings,
+    pub aim_stick: StickSettings
+}
+
+impl Default for GamepadBindings {
+    /// The layout of a PS5 controller.
+    fn default() -> Self {
+        Self {
+            move_x_axis: 0,
+            move_y_axis: 1,
+            aim_x_axis: 2,
+            aim_y_axis: 3,
+            pause_button: 9,
+            confirm_button: 0,
+            rebind_button: 8,
+            continue_button: 3,
+            move_stick: StickSettings::default(),
+            aim_stick: StickSettings::default()
+        }
+    }
+}
+
+/// Bindings for every controller, with profiles keyed by the name the controller reports.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct Bindings {
+    /// Used by controllers without a profile of their own.
+    pub default: GamepadBindings,
+    pub profiles: BTreeMap<String, GamepadBindings>
+}
+
+impl Bindings {
+    pub fn for_controller(&self, name: Option<&str>) -> &GamepadBindings {
+        name.and_then(|name| self.profiles.get(name)).unwrap_or(&self.default)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum RebindStep {
+    MoveX,
+    MoveY,
+    AimX,
+    AimY,
+    Pause,
+    Confirm,
+    Continue,
+    Done
+}
+
+/// The rebinding screen: asks for each action in turn and records the axis or button used.
+pub struct Rebinder {
+    bindings: GamepadBindings,
+    controller_id: Option<u32>,
+    step: RebindStep
+}
+
+impl Rebinder {
+    /// Starts rebinding, beginning from the controller's current bindings. When `controller_id`
+    /// is `None` the first controller touched is the one rebound.
+    pub fn new(controller_id: Option<u32>, current: &GamepadBindings) -> Self {
+        Self {
+            bindings: current.clone(),
+            controller_id,
+            step: RebindStep::MoveX
+        }
+    }
+
+    pub fn get_controller_id(&self) -> Option<u32> {
+        self.controller_id
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.step == RebindStep::Done
+    }
+
+    pub fn get_bindings(&self) -> &GamepadBindings {
+        &self.bindings
+    }
+
+    pub fn get_prompt(&self) -> &'static str {
+        match self.step {
+            RebindStep::MoveX => "Push the stick to move left or right",
+            RebindStep::MoveY => "Push the stick to move up or down",
+            RebindStep::AimX => "Push the stick to aim left or right",
+            RebindStep::AimY => "Push the stick to aim up or down",
+            RebindStep::Pause => "Press the button to pause",
+            RebindStep::Confirm => "Press the button to confirm and continue",
+            RebindStep::Continue => "Press the button to continue a saved game",
+            RebindStep::Done => "Controller set up"
+        }
+    }
+
+    fn is_from_controller(&mut self, id: u32) -> bool {
+        *self.controller_id.get_or_insert(id) == id
+    }
+
+    pub fn handle_event(&mut self, event: &Event) {
+        if let Some(args) = event.controller_axis_args() {
+            if args.position.abs() < REBIND_AXIS_THRESHOLD || !self.is_from_controller(args.id) {
+                return;
+            }
+
+            // each axis can only do one job, and the move axes are taken before the aim axes
+            let taken: &[u8] = match self.step {
+                RebindStep::MoveY => &[self.bindings.move_x_axis],
+                RebindStep::AimX => &[self.bindings.move_x_axis, self.bindings.move_y_axis],
+                RebindStep::AimY => &[self.bindings.move_x_axis, self.bindings.move_y_axis, self.bindings.aim_x_axis],
+                _ => &[]
+            };
+            if taken.contains(&args.axis) {
+                return;
+            }
+
+            self.step = match self.step {
+                RebindStep::MoveX => {
+                    self.bindings.move_x_axis = args.axis;
+                    RebindStep::MoveY
+                }
+                RebindStep::MoveY => {
+                    self.bindings.move_y_axis = args.axis;
+                    RebindStep::AimX
+                }
+                RebindStep::AimX => {
+                    self.bindings.aim_x_axis = args.axis;
+                    RebindStep::AimY
+                }
+                RebindStep::AimY => {
+                    self.bindings.aim_y_axis = args.axis;
+                    RebindStep::Pause
+                }
+                step => step
+            };
+        }
+
+        if let Some(Button::Controller(button)) = event.release_args() {
+            if !self.is_from_controller(button.id) {
+                return;
+            }
+            // each button can only do one job, like the axes, and the rebind button already has one
+            let rebind = self.bindings.rebind_button;
+            self.step = match self.step {
+                RebindStep::Pause if button.button != rebind => {
+                    self.bindings.pause_button = button.button;
+                    RebindStep::Confirm
+                }
+                RebindStep::Confirm if ![rebind, self.bindings.pause_button].contains(&button.button) => {
+                    self.bindings.confirm_button = button.button;
+                    RebindStep::Continue
+                }
+                RebindStep::Continue if ![rebind, self.bindings.pause_button, self.bindings.confirm_button].contains(&button.button) => {
+                    self.bindings.continue_button = button.button;
+                    RebindStep::Done
+                }
+                step => step
+            };
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use std::f64::consts::TAU;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use crate::clock::Clock;
+use crate::game::Game;
+use crate::game_sprite::GameSprite;
+use crate::steering::{center, towards, turn};
+use crate::world::SpriteSize;
+
+pub const BOSS_FRAMES: u32 = 3;
+
+const FRAME_DURATION_MILLIS: Duration = Duration::from_millis(150);
+// how far above the player the boss likes to hover
+const HOVER_DISTANCE: f64 = 250.0;
+// shots in the second phase's fan, and how far apart they are in radians
+const FAN_SHOTS: u32 = 5;
+const FAN_SPREAD: f64 = 0.25;
+// shots in the last phase's ring
+const RING_SHOTS: u32 = 12;
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum BossState {
+    Alive,
+    Dying,
+    Dead
+}
+
+/// The enemy guarding a boss level. It gets more dangerous as it's worn down: aimed shots
+/// first, then a fan of them, then rings of shots while it speeds up.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Boss {
+    frames: u32,
+    hit_points: u32,
+    last_frame_change: Duration,
+    last_shot: Duration,
+    max_hit_points: u32,
+    sprite: GameSprite,
+    sprite_index: u32,
+    state: BossState,
+    velocity: (f64, f64)
+}
+
+impl Boss {
+    pub fn new(x: f64, y: f64, size: SpriteSize, frames: u32, hit_points: u32, now: Duration) -> Self {
+        Self {
+            frames,
+            hit_points,
+            last_frame_change: now,
+            last_shot: now,
+            max_hit_points: hit_points,
+            sprite: GameSprite::new(x, y, size.width as f64, size.height as f64),
+            sprite_index: 0,
+            state: BossState::Alive,
+            velocity: (0.0, 0.0)
+        }
+    }
+
+    pub fn get_sprite(&self) -> GameSprite {
+        self.sprite
+    }
+
+    pub fn get_sprite_index(&self) -> u32 {
+        self.sprite_index
+    }
+
+    pub fn get_state(&self) -> BossState {
+        self.state
+    }
+
+    /// How much health is left, from 1 down to 0, for the health bar.
+    pub fn get_health(&self) -> f64 {
+        self.hit_points as f64 / self.max_hit_points.max(1) as f64
+    }
+
+    /// Which attack phase it's in, from 1 to 3, moving on as it loses health.
+    pub fn get_phase(&self) -> u32 {
+        match self.get_health() {
+            h if h > 2.0 / 3.0 => 1,
+            h if h > 1.0 / 3.0 => 2,
+            _ => 3
+        }
+    }
+
+    /// Takes a laser hit. Gives whether that killed it.
+    pub fn hit(&mut self) -> bool {
+        if self.state != BossState::Alive {
+            return false;
+        }
+        self.hit_points = self.hit_points.saturating_sub(1);
+        if self.hit_points == 0 {
+            self.state = BossState::Dying;
+            self.sprite.shatter_start();
+        }
+        self.hit_points == 0
+    }
+
+    /// Moves and attacks. Gives the shots fired, as where from and where to.
+    pub fn update(&mut self, game: &Game, clock: &dyn Clock, dt: f64) -> Vec<((f64, f64), (f64, f64))> {
+        let tuning = &game.tuning;
+        match self.state {
+            BossState::Alive => {
+                if clock.since(self.last_frame_change) >= FRAME_DURATION_MILLIS {
+                    self.sprite_index = (self.sprite_index + 1).rem_euclid(self.frames);
+                    self.last_frame_change = clock.now();
+                }
+
+                // hovers above the player, or below when there's no room
+                let position = center(&self.sprite.get_position());
+                let player = center(&game.player);
+                let hover_y = if player.1 - HOVER_DISTANCE > self.sprite.height / 2.0 { -HOVER_DISTANCE } else { HOVER_DISTANCE };
+                let (x, y) = towards(position, (player.0, player.1 + hover_y));
+                let speed = tuning.boss_speed * self.get_phase() as f64;
+                self.velocity = turn(self.velocity, (x * speed, y * speed), tuning.enemy_turn_rate * dt);
+                self.sprite.x += self.velocity.0 * dt;
+                self.sprite.y += self.velocity.1 * dt;
+                self.sprite.keep_inside(game.screen_width, game.screen_height);
+
+                if clock.since(self.last_shot) < tuning.get_boss_fire_interval() {
+                    return Vec::new();
+                }
+                self.last_shot = clock.now();
+                let (aim_x, aim_y) = towards(position, player);
+                let aim = aim_y.atan2(aim_x);
+                let angles: Vec<f64> = match self.get_phase() {
+                    1 => vec![aim],
+                    2 => (0..FAN_SHOTS).map(|i| aim + (i as f64 - (FAN_SHOTS - 1) as f64 / 2.0) * FAN_SPREAD).collect(),
+                    _ => (0..RING_SHOTS).map(|i| aim + i as f64 * TAU / RING_SHOTS as f64).collect()
+                };
+                angles.into_iter().map(|a| (position, (position.0 + a.cos(), position.1 + a.sin()))).collect()
+            }
+            BossState::Dying => {
+                if self.sprite.shatter_update(tuning.enemy_die_speed * dt, game.screen_width, game.screen_height) {
+                    self.state = BossState::Dead;
+                }
+                Vec::new()
+            }
+            BossState::Dead => Vec::new()
+        }
+    }
+}
+
+/// Where a boss level's boss lives: the boss while there is one, and the size to make it.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Arena {
+    boss: Option<Boss>,
+    sprite_frames: u32,
+    sprite_size: SpriteSize
+}
+
+impl Arena {
+    pub fn new(sprite_size: SpriteSize, sprite_frames: u32) -> Self {
+        Self { boss: None, sprite_frames, sprite_size }
+    }
+
+    pub fn get_boss(&self) -> Option<&Boss> {
+        self.boss.as_ref()
+    }
+
+    pub fn get_boss_mut(&mut self) -> Option<&mut Boss> {
+        self.boss.as_mut()
+    }
+
+    /// Brings in a boss, centered across the top of the playfield.
+    pub fn place(&mut self, screen_width: f64, hit_points: u32, now: Duration) {
+        let x = (screen_width - self.sprite_size.width as f64) / 2.0;
+        self.boss = Some(Boss::new(x, 0.0, self.sprite_size, self.sprite_frames, hit_points, now));
+    }
+
+    /// Moves and attacks, and clears the boss away once it's finished shattering. Gives the shots
+    /// fired, as where from and where to.
+    pub fn update(&mut self, game: &Game, clock: &dyn Clock, dt: f64) -> Vec<((f64, f64), (f64, f64))> {
+        let shots = self.boss.as_mut().map_or_else(Vec::new, |boss| boss.update(game, clock, dt));
+        if self.boss.as_ref().is_some_and(|boss| boss.state == BossState::Dead) {
+            self.boss = None;
+        }
+        shots
+    }
+
+    pub fn reset(&mut self) {
+        self.boss = None;
+    }
+}
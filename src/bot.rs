@@ -0,0 +1,90 @@
+use crate::black_hole::BlackHoleState;
+use crate::boss::BossState;
+use crate::enemy::EnemyState;
+use crate::game::GameState;
+use crate::input::{InputFrame, InputSource};
+use crate::planets::PlanetState;
+use crate::player::PlayerState;
+use crate::rect::Rect;
+use crate::world::World;
+
+// enemies further away than this are left alone
+const BOT_FIRE_RANGE: f64 = 300.0;
+
+fn center(r: Rect) -> (f64, f64) {
+    (r.x as f64 + r.w as f64 / 2.0, r.y as f64 + r.h as f64 / 2.0)
+}
+
+fn towards(from: (f64, f64), to: (f64, f64)) -> (f64, f64) {
+    let x = to.0 - from.0;
+    let y = to.1 - from.1;
+    let length = x.hypot(y);
+    if length < 1.0 {
+        return (0.0, 0.0);
+    }
+    (x / length, y / length)
+}
+
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    (b.0 - a.0).hypot(b.1 - a.1)
+}
+
+fn nearest(from: (f64, f64), targets: impl Iterator<Item = Rect>) -> Option<(f64, f64)> {
+    targets.map(center).min_by(|a, b| distance(from, *a).total_cmp(&distance(from, *b)))
+}
+
+/// A simple computer player: it fetches the nearest planet, tows it to the nearest black hole
+/// and shoots whatever enemy gets close. Useful for soak tests and as an attract mode.
+#[derive(Default)]
+pub struct BotInput {
+    confirm_next: bool
+}
+
+impl BotInput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl InputSource for BotInput {
+    fn next_frame(&mut self, world: &World) -> InputFrame {
+        let mut frame = InputFrame::default();
+        let game = world.get_game();
+        let player = world.get_player();
+        if game.state != GameState::Running {
+            // alternate so every press is a separate release
+            self.confirm_next = !self.confirm_next;
+            frame.confirm = self.confirm_next;
+            return frame;
+        }
+
+        let position = center(player.get_sprite().get_position());
+        let target = if player.get_state() == PlayerState::Towing {
+            nearest(position, world.get_black_holes().iter()
+                .filter(|h| h.get_state() == BlackHoleState::Open)
+                .map(|h| h.get_sprite().get_position()))
+        } else {
+            nearest(position, world.get_planets().iter()
+                .filter(|p| p.get_state() == PlanetState::NotTowed)
+                .map(|p| p.get_sprite().get_position()))
+        };
+        if let Some(target) = target {
+            (frame.move_x, frame.move_y) = towards(position, target);
+        }
+
+        let boss = world.get_arena().get_boss()
+            .filter(|b| b.get_state() == BossState::Alive)
+            .map(|b| b.get_sprite().get_position());
+        let enemy = nearest(position, world.get_enemies().iter()
+            .filter(|e| e.get_state() == EnemyState::Alive)
+            .map(|e| e.get_sprite().get_position())
+            .chain(boss));
+        if let Some(enemy) = enemy {
+            if distance(position, enemy) < BOT_FIRE_RANGE {
+                (frame.aim_x, frame.aim_y) = towards(position, enemy);
+                frame.fire = frame.aim_x != 0.0 || frame.aim_y != 0.0;
+            }
+        }
+        frame
+    }
+}
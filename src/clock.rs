@@ -0,0 +1,62 @@
+use std::time::{Duration, Instant};
+
+/// A source of time for everything that waits, animates or rate-limits.
+pub trait Clock {
+    /// Time elapsed since the clock started. Never goes backwards.
+    fn now(&self) -> Duration;
+
+    /// Time elapsed since `earlier`, a value previously returned by `now`.
+    fn since(&self, earlier: Duration) -> Duration {
+        self.now().saturating_sub(earlier)
+    }
+}
+
+/// Wall-clock time, backed by the monotonic `Instant`.
+pub struct SystemClock {
+    start: Instant
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        Self { start: Instant::now() }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// A clock that only moves when told to. The simulation runs on one of these, advanced a tick
+/// at a time, and tests can use one to drive timers precisely.
+#[derive(Clone, Copy, Default)]
+pub struct ManualClock {
+    now: Duration
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self { now: Duration::ZERO }
+    }
+
+    pub fn advance(&mut self, duration: Duration) {
+        self.now += duration;
+    }
+
+    pub fn set(&mut self, now: Duration) {
+        self.now = now;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        self.now
+    }
+}
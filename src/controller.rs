@@ -1,25 +1,97 @@
-use piston::ControllerAxisArgs;
+use std::collections::HashMap;
+use piston::{Button, ControllerAxisArgs, ControllerAxisEvent, Event, ReleaseEvent};
+use serde::{Deserialize, Serialize};
+use crate::bindings::{Bindings, GamepadBindings};
+use crate::input::{InputFrame, InputSource};
+use crate::world::World;
 
-#[derive(Clone, Copy)]
+/// How a stick's raw position is turned into movement.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResponseCurve {
+    #[default]
+    Linear,
+    /// Finer control near the center.
+    Quadratic,
+    /// Points of `[input, output]` between 0 and 1, joined by straight lines. The curve starts
+    /// at `[0, 0]` and ends at `[1, 1]` unless the points say otherwise.
+    Custom(Vec<[f64; 2]>)
+}
+
+impl ResponseCurve {
+    pub fn apply(&self, value: f64) -> f64 {
+        let value = value.clamp(0.0, 1.0);
+        let output = match self {
+            ResponseCurve::Linear => value,
+            ResponseCurve::Quadratic => value * value,
+            ResponseCurve::Custom(points) => {
+                let mut from = [0.0, 0.0];
+                let mut to = [1.0, 1.0];
+                for point in points {
+                    if value <= point[0] {
+                        to = *point;
+                        break;
+                    }
+                    from = *point;
+                }
+                let span = to[0] - from[0];
+                if span <= 0.0 {
+                    to[1]
+                } else {
+                    from[1] + (to[1] - from[1]) * (value - from[0]) / span
+                }
+            }
+        };
+        output.clamp(0.0, 1.0)
+    }
+}
+
+/// Deadzones and response curve for one stick.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct StickSettings {
+    /// Anything closer to the center than this counts as centered, so drift doesn't move the ship.
+    pub deadzone: f64,
+    /// The outermost part of the stick's travel, which counts as fully pushed.
+    pub outer_deadzone: f64,
+    pub curve: ResponseCurve
+}
+
+impl Default for StickSettings {
+    fn default() -> Self {
+        Self { deadzone: 0.15, outer_deadzone: 0.05, curve: ResponseCurve::Linear }
+    }
+}
+
+impl StickSettings {
+    /// Applies the radial deadzones and the curve to a raw stick position, keeping its direction.
+    pub fn apply(&self, x: f64, y: f64) -> (f64, f64) {
+        let distance = x.hypot(y);
+        if distance <= self.deadzone {
+            return (0.0, 0.0);
+        }
+
+        let travel = (1.0 - self.deadzone - self.outer_deadzone).max(f64::EPSILON);
+        let scaled = self.curve.apply((distance - self.deadzone) / travel);
+        (x / distance * scaled, y / distance * scaled)
+    }
+}
+
+#[derive(Clone, Copy, Default)]
 pub struct StickPosition {
     degrees: f64,
     x: f64,
-    y: f64,
-    screen_x: f64,
-    screen_y: f64,
-    screen_width: f64,
-    screen_height: f64
+    y: f64
 }
 
 impl StickPosition {
-    pub fn new(x: f64, y: f64, screen_width: f64, screen_height: f64) -> Self {
-        Self {
-            degrees: 0.0, x, y,
-            screen_x: 0.0, screen_y: 0.0,
-            screen_width, screen_height
-        }
+    pub fn new(x: f64, y: f64) -> Self {
+        let mut position = Self { degrees: 0.0, x, y };
+        position.update();
+        position
     }
 
+    /// The direction the stick was last pushed in, kept when it is released.
     pub fn get_degrees(self) -> f64 {
         self.degrees
     }
@@ -32,34 +104,35 @@ impl StickPosition {
         self.y
     }
 
-    pub fn get_screen_x(self) -> f64 {
-        self.screen_x
+    pub fn is_centered(self) -> bool {
+        self.x == 0.0 && self.y == 0.0
     }
 
-    pub fn get_screen_y(self) -> f64 {
-        self.screen_y
+    /// Moves the stick to a raw position, after the deadzones and curve in `settings`.
+    pub fn set(&mut self, x: f64, y: f64, settings: &StickSettings) {
+        (self.x, self.y) = settings.apply(x, y);
+        self.update();
     }
 
     pub fn update(&mut self) {
-        // analogue x and y axes move between -1 and 1
-        self.screen_x = ((self.x - -1.0) / 2.0) * self.screen_width;
-        self.screen_y = ((self.y - -1.0) / 2.0) * self.screen_height;
-        self.degrees = self.y.atan2(self.x).to_degrees();
+        // analogue x and y axes move between -1 and 1, and a centered stick has no direction
+        if !self.is_centered() {
+            self.degrees = self.y.atan2(self.x).to_degrees();
+        }
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Default)]
 pub struct Controller {
+    left_raw: (f64, f64),
     left_stick: StickPosition,
+    right_raw: (f64, f64),
     right_stick: StickPosition
 }
 
 impl Controller {
-    pub fn new(screen_width: f64, screen_height: f64) -> Self {
-        Self {
-            left_stick: StickPosition::new(0.0, 0.0, screen_width, screen_height),
-            right_stick: StickPosition::new(0.0, 0.0, screen_width, screen_height)
-        }
+    pub fn new() -> Self {
+        Self::default()
     }
 
     pub fn get_left_stick(self) -> StickPosition {
@@ -70,17 +143,79 @@ impl Controller {
         self.right_stick
     }
 
-    pub fn update(&mut self, args: ControllerAxisArgs) {
-        let position = (args.position * 10.0).trunc() / 10.0;
-        match args.axis {
-            0 => self.left_stick.x = position,
-            1 => self.left_stick.y = position,
-            2 => self.right_stick.x = position,
-            3 => self.right_stick.y = position,
-            _ => {}
+    pub fn update(&mut self, args: ControllerAxisArgs, bindings: &GamepadBindings) {
+        let position = args.position.clamp(-1.0, 1.0);
+        if args.axis == bindings.move_x_axis {
+            self.left_raw.0 = position;
+        } else if args.axis == bindings.move_y_axis {
+            self.left_raw.1 = position;
+        } else if args.axis == bindings.aim_x_axis {
+            self.right_raw.0 = position;
+        } else if args.axis == bindings.aim_y_axis {
+            self.right_raw.1 = position;
+        }
+
+        self.left_stick.set(self.left_raw.0, self.left_raw.1, &bindings.move_stick);
+        self.right_stick.set(self.right_raw.0, self.right_raw.1, &bindings.aim_stick);
+    }
+}
+
+/// A gamepad: by default the left stick moves, the right stick aims and fires, Options pauses
+/// and the confirm button, or any other button without a job, confirms. Each controller uses the
+/// bindings for its name.
+#[derive(Default)]
+pub struct GamepadInput {
+    bindings: Bindings,
+    confirm: bool,
+    controller: Controller,
+    names: HashMap<u32, String>,
+    pause: bool
+}
+
+impl GamepadInput {
+    /// `names` maps controller ids to the names the controllers report.
+    pub fn new(bindings: Bindings, names: HashMap<u32, String>) -> Self {
+        Self { bindings, names, ..Self::default() }
+    }
+
+    fn bindings_for(&self, id: u32) -> &GamepadBindings {
+        self.bindings.for_controller(self.names.get(&id).map(String::as_str))
+    }
+}
+
+impl InputSource for GamepadInput {
+    fn handle_event(&mut self, event: &Event) {
+        if let Some(args) = event.controller_axis_args() {
+            let bindings = self.bindings_for(args.id).clone();
+            self.controller.update(args, &bindings);
+        }
+
+        if let Some(Button::Controller(button)) = event.release_args() {
+            let bindings = self.bindings_for(button.id);
+            if button.button == bindings.pause_button {
+                self.pause = true;
+            } else if button.button == bindings.confirm_button
+                || (button.button != bindings.rebind_button && button.button != bindings.continue_button) {
+                self.confirm = true;
+            }
         }
+    }
 
-        self.left_stick.update();
-        self.right_stick.update();
+    fn next_frame(&mut self, _world: &World) -> InputFrame {
+        let left_stick = self.controller.get_left_stick();
+        let right_stick = self.controller.get_right_stick();
+        let frame = InputFrame {
+            move_x: left_stick.get_x(),
+            move_y: left_stick.get_y(),
+            aim_x: right_stick.get_x(),
+            aim_y: right_stick.get_y(),
+            fire: !right_stick.is_centered(),
+            confirm: self.confirm,
+            pause: self.pause,
+            difficulty: None
+        };
+        self.confirm = false;
+        self.pause = false;
+        frame
     }
 }
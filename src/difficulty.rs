@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+use crate::tuning::Tuning;
+
+// spawning never gets faster than this, however hard the level
+const MIN_SPAWN_INTERVAL_MILLIS: u64 = 50;
+
+/// How hard the enemies start out and how quickly they get harder from level to level.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+    Insane
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 4] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard, Difficulty::Insane];
+
+    pub fn get_name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+            Difficulty::Insane => "Insane"
+        }
+    }
+
+    /// The one after this, going back to the easiest after the hardest.
+    pub fn next(self) -> Self {
+        Self::ALL[(self.to_index() as usize + 1) % Self::ALL.len()]
+    }
+
+    pub fn to_index(self) -> u8 {
+        Self::ALL.iter().position(|&d| d == self).unwrap_or(0) as u8
+    }
+
+    pub fn from_index(index: u8) -> Option<Self> {
+        Self::ALL.get(index as usize).copied()
+    }
+
+    // how much harder than the tuning the first level is, and how much faster levels get harder
+    fn get_factors(self) -> (f64, f64) {
+        match self {
+            Difficulty::Easy => (0.75, 0.5),
+            Difficulty::Normal => (1.0, 1.0),
+            Difficulty::Hard => (1.25, 1.5),
+            Difficulty::Insane => (1.6, 2.0)
+        }
+    }
+
+    /// How much harder than the tuning `level` is, counting levels from 1.
+    pub fn get_scale(self, tuning: &Tuning, level: u32) -> f64 {
+        let (start, growth) = self.get_factors();
+        let levels = level.saturating_sub(1) as f64;
+        // the cap holds on every difficulty, however hard it starts
+        (start * (1.0 + tuning.level_scaling * growth * levels)).min(tuning.max_level_scaling)
+    }
+
+    /// The tuning with its enemies made as hard as `level` is on this difficulty: faster, more
+    /// of them, spawning sooner and more often, and bosses tougher.
+    pub fn apply(self, tuning: &Tuning, level: u32) -> Tuning {
+        let scale = self.get_scale(tuning, level);
+        let mut tuning = tuning.clone();
+        tuning.enemy_speed *= scale;
+        tuning.max_enemies = (tuning.max_enemies as f64 * scale).round().max(1.0) as usize;
+        tuning.enemy_spawn_delay_millis = (tuning.enemy_spawn_delay_millis as f64 / scale) as u64;
+        tuning.enemy_spawn_interval_millis = ((tuning.enemy_spawn_interval_millis as f64 / scale) as u64)
+            .max(MIN_SPAWN_INTERVAL_MILLIS);
+        tuning.boss_hit_points = (tuning.boss_hit_points as f64 * scale).round().max(1.0) as u32;
+        tuning
+    }
+}
@@ -1,46 +1,143 @@
-use std::collections::HashMap;
-use std::rc::Rc;
-use std::time::{Duration, SystemTime};
-use graphics::{Context, ImageSize};
-use opengl_graphics::{GlGraphics, Texture};
-use piston_window::TextureSettings;
+use std::collections::btree_map::Values;
+use std::collections::BTreeMap;
+use std::time::Duration;
 use rand::Rng;
-use rust_embed::EmbeddedFile;
-use sdl2::rect::Rect;
-use sprite::Sprite;
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
+use crate::behavior::{Behavior, Chaser, Gunner, Orbiter, Wanderer};
+use crate::clock::Clock;
 use crate::game::Game;
 use crate::game_sprite::GameSprite;
+use crate::rng::GameRng;
+use crate::save::HasSprite;
+use crate::steering::{avoidance, cohesion, separation, truncate, turn};
+use crate::world::SpriteSize;
+
+pub const ENEMY_FRAMES: u32 = 3;
+/// How many enemy sprite sheets there are, one for every kind that spawns.
+pub const ENEMY_SHEETS: usize = 5;
 
-const ENEMY_MOVE_INCREMENT: f64 = 0.25;
-const ENEMY_DIE_INCREMENT: f64 = 1.0;
 const FRAME_DURATION_MILLIS: Duration = Duration::from_millis(100);
-const MAX_ENEMIES: usize = 75;
-const WAIT_TO_SPAWN_DURATION: Duration = Duration::from_millis(2000);
 
-#[derive(Clone, Copy, PartialEq)]
+/// The kinds of enemy. Fragments only come from splitters; the rest spawn from black holes.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EnemyKind {
+    Chaser,
+    Wanderer,
+    Orbiter,
+    Splitter,
+    Gunner,
+    Fragment
+}
+
+impl EnemyKind {
+    /// The kinds that spawn, in the order levels bring them in.
+    pub const SPAWNED: [EnemyKind; ENEMY_SHEETS] = [
+        EnemyKind::Chaser, EnemyKind::Wanderer, EnemyKind::Orbiter, EnemyKind::Splitter, EnemyKind::Gunner
+    ];
+
+    /// The kinds that spawn on `level`, counting from 1: a new one every level until all of
+    /// them do.
+    pub fn for_level(level: u32) -> Vec<EnemyKind> {
+        Self::SPAWNED[..(level as usize).clamp(1, ENEMY_SHEETS)].to_vec()
+    }
+
+    /// Which sprite sheet to draw this kind with.
+    pub fn get_sheet(self) -> usize {
+        match self {
+            EnemyKind::Chaser => 0,
+            EnemyKind::Wanderer => 1,
+            EnemyKind::Orbiter => 2,
+            EnemyKind::Splitter | EnemyKind::Fragment => 3,
+            EnemyKind::Gunner => 4
+        }
+    }
+
+    /// How big this kind is drawn next to its sprite sheet.
+    pub fn get_scale(self) -> f64 {
+        if self == EnemyKind::Fragment { 0.5 } else { 1.0 }
+    }
+
+    /// This kind's speed, as a multiple of the tuning's enemy speed.
+    pub fn get_speed(self) -> f64 {
+        match self {
+            EnemyKind::Chaser => 1.0,
+            EnemyKind::Wanderer => 1.5,
+            EnemyKind::Orbiter => 2.0,
+            EnemyKind::Splitter => 0.75,
+            EnemyKind::Gunner => 1.0,
+            EnemyKind::Fragment => 1.25
+        }
+    }
+
+    /// How many laser hits this kind takes to kill.
+    pub fn get_hit_points(self) -> u32 {
+        match self {
+            EnemyKind::Chaser | EnemyKind::Wanderer | EnemyKind::Fragment => 1,
+            EnemyKind::Orbiter | EnemyKind::Gunner => 2,
+            EnemyKind::Splitter => 3
+        }
+    }
+
+    /// What killing this kind scores, as a multiple of the tuning's enemy points.
+    pub fn get_points(self) -> u32 {
+        match self {
+            EnemyKind::Chaser | EnemyKind::Fragment => 1,
+            EnemyKind::Wanderer => 2,
+            EnemyKind::Orbiter => 3,
+            EnemyKind::Gunner => 4,
+            EnemyKind::Splitter => 5
+        }
+    }
+
+    /// Whether this kind breaks into fragments when it's killed.
+    pub fn splits(self) -> bool {
+        self == EnemyKind::Splitter
+    }
+
+    fn new_behavior(self, rng: &mut GameRng) -> Behavior {
+        match self {
+            EnemyKind::Wanderer => Behavior::Wander(Wanderer::default()),
+            EnemyKind::Orbiter => Behavior::Orbit(Orbiter::new(rng.gen_range(0.0, std::f64::consts::TAU))),
+            EnemyKind::Gunner => Behavior::Shoot(Gunner::default()),
+            EnemyKind::Chaser | EnemyKind::Splitter | EnemyKind::Fragment => Behavior::Chase(Chaser {})
+        }
+    }
+}
+
+#[derive(Clone, Copy, Deserialize, PartialEq, Serialize)]
 pub enum EnemyState {
     Alive,
     Dying,
     Dead
 }
 
+#[derive(Clone, Deserialize, Serialize)]
 pub struct Enemy {
+    behavior: Behavior,
+    hit_points: u32,
+    kind: EnemyKind,
     sprite: GameSprite,
     sprite_index: u32,
     state: EnemyState,
     frames: u32,
-    last_frame_change: SystemTime
+    last_frame_change: Duration,
+    velocity: (f64, f64)
 }
 
 impl Enemy {
-    pub fn new(x: f64, y: f64, width: u32, height: u32, frames: u32) -> Self {
+    pub fn new(kind: EnemyKind, behavior: Behavior, x: f64, y: f64, size: SpriteSize, frames: u32, now: Duration) -> Self {
         Self {
-            sprite: GameSprite::new(x, y, width as f64, height as f64),
+            behavior,
+            hit_points: kind.get_hit_points(),
+            kind,
+            sprite: GameSprite::new(x, y, size.width as f64, size.height as f64),
             sprite_index: 0,
             state: EnemyState::Alive,
             frames,
-            last_frame_change: SystemTime::now()
+            last_frame_change: now,
+            velocity: (0.0, 0.0)
         }
     }
 
@@ -49,6 +146,27 @@ impl Enemy {
         self.sprite.shatter_start();
     }
 
+    /// Takes a laser hit. Gives whether that killed it.
+    pub fn hit(&mut self) -> bool {
+        if self.state != EnemyState::Alive {
+            return false;
+        }
+        self.hit_points = self.hit_points.saturating_sub(1);
+        if self.hit_points == 0 {
+            self.dying();
+        }
+        self.hit_points == 0
+    }
+
+    /// Where this enemy is about to shoot, while it takes aim.
+    pub fn get_aim(&self) -> Option<(f64, f64)> {
+        self.behavior.get().get_aim()
+    }
+
+    pub fn get_kind(&self) -> EnemyKind {
+        self.kind
+    }
+
     pub fn get_sprite(&self) -> GameSprite {
         self.sprite
     }
@@ -61,71 +179,87 @@ impl Enemy {
         self.state
     }
 
-    fn update(&mut self, player: Rect, screen_height: f64, screen_width: f64) {
+    pub fn get_center(&self) -> (f64, f64) {
+        (self.sprite.x + self.sprite.width / 2.0, self.sprite.y + self.sprite.height / 2.0)
+    }
+
+    // `neighbors` are the middles of every living enemy, this one at `index`. Gives where it
+    // shoots at, if it does.
+    fn update(
+        &mut self, game: &Game, clock: &dyn Clock, rng: &mut GameRng,
+        neighbors: &[(f64, f64)], index: usize, dt: f64
+    ) -> Option<(f64, f64)> {
         match self.state {
             EnemyState::Alive => {
-                if self.last_frame_change.elapsed().unwrap() >= FRAME_DURATION_MILLIS {
+                if clock.since(self.last_frame_change) >= FRAME_DURATION_MILLIS {
                     self.sprite_index = (self.sprite_index + 1).rem_euclid(self.frames);
-                    self.last_frame_change = SystemTime::now();
-                }
-                if (player.x as f64) < self.sprite.x {
-                    self.sprite.x -= ENEMY_MOVE_INCREMENT;
-                } else {
-                    self.sprite.x += ENEMY_MOVE_INCREMENT;
-                }
-
-                if (player.y as f64) < self.sprite.y {
-                    self.sprite.y -= ENEMY_MOVE_INCREMENT;
-                } else {
-                    self.sprite.y += ENEMY_MOVE_INCREMENT;
+                    self.last_frame_change = clock.now();
                 }
+                let tuning = &game.tuning;
+                let speed = tuning.enemy_speed * self.kind.get_speed();
+                let position = self.get_center();
+                let forces = [
+                    (self.behavior.get_mut().desired_velocity(&self.sprite, game, rng, speed, dt), 1.0),
+                    (separation(position, index, neighbors, tuning.enemy_separation_radius), tuning.enemy_separation),
+                    (cohesion(position, index, neighbors, tuning.enemy_cohesion_radius), tuning.enemy_cohesion),
+                    (avoidance(position, &game.obstacles, tuning.enemy_avoidance_radius), tuning.enemy_avoidance)
+                ];
+                let steering = forces.iter().fold((0.0, 0.0), |sum, ((x, y), weight)| (sum.0 + x * weight, sum.1 + y * weight));
+                let desired = truncate(steering, 1.0);
+                self.velocity = turn(self.velocity, (desired.0 * speed, desired.1 * speed), tuning.enemy_turn_rate * dt);
+                self.sprite.x += self.velocity.0 * dt;
+                self.sprite.y += self.velocity.1 * dt;
+                self.sprite.keep_inside(game.screen_width, game.screen_height);
+                return self.behavior.get_mut().shoot(&self.sprite, game, clock.now());
             }
             EnemyState::Dying => {
-                if self.sprite.shatter_update(ENEMY_DIE_INCREMENT, screen_width, screen_height) {
+                if self.sprite.shatter_update(game.tuning.enemy_die_speed * dt, game.screen_width, game.screen_height) {
                     self.state = EnemyState::Dead;
                 }
             }
             EnemyState::Dead => {}
         }
+        None
     }
 }
 
+impl HasSprite for Enemy {
+    fn get_sprite(&self) -> GameSprite {
+        self.sprite
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
 enum EnemiesState {
     Running,
     WaitingToSpawn,
     WaitingForSpawnPoints
 }
 
+#[derive(Clone, Deserialize, Serialize)]
 pub struct Enemies {
-    enemies: HashMap<Uuid, Enemy>,
-    last_enemy: SystemTime,
-    sprite: Sprite<Texture>,
-    sprite_height: u32,
-    sprite_width: u32,
+    #[serde(with = "crate::save::sprite_map")]
+    enemies: BTreeMap<Uuid, Enemy>,
+    last_enemy: Duration,
     sprite_frames: u32,
+    sprite_sizes: [SpriteSize; ENEMY_SHEETS],
     state: EnemiesState,
-    wait_start: SystemTime
+    wait_start: Duration
 }
 
 impl Enemies {
-    pub fn new(sprite_file: &EmbeddedFile, sprite_frames: u32) -> Self {
-        let image = image::load_from_memory(sprite_file.data.as_ref()).unwrap();
-        let texture = Texture::from_image(image.as_rgba8().unwrap(), &TextureSettings::new());
-        let sprite_height = texture.get_height();
-        let sprite_width = texture.get_width() / sprite_frames;
+    pub fn new(sprite_sizes: [SpriteSize; ENEMY_SHEETS], sprite_frames: u32) -> Self {
         Self {
-            enemies: HashMap::new(),
-            last_enemy: SystemTime::now(),
-            sprite: Sprite::from_texture(Rc::new(texture)),
+            enemies: BTreeMap::new(),
+            last_enemy: Duration::ZERO,
             sprite_frames,
-            sprite_height,
-            sprite_width,
+            sprite_sizes,
             state: EnemiesState::WaitingForSpawnPoints,
-            wait_start: SystemTime::now()
+            wait_start: Duration::ZERO
         }
     }
 
-    pub fn get_enemies(&mut self) -> &mut HashMap<Uuid, Enemy> {
+    pub fn get_enemies(&mut self) -> &mut BTreeMap<Uuid, Enemy> {
         &mut self.enemies
     }
 
@@ -133,56 +267,72 @@ impl Enemies {
         self.enemies.remove(id);
     }
 
-    pub fn draw(&mut self, ctx: Context, gl: &mut GlGraphics) {
-        for enemy in self.enemies.values_mut() {
-            match enemy.get_state() {
-                EnemyState::Alive => {
-                    self.sprite.set_src_rect([
-                        self.sprite_width as f64 * enemy.get_sprite_index() as f64,
-                        0.0,
-                        self.sprite_width as f64,
-                        self.sprite_height as f64
-                    ]);
-                    enemy.sprite.draw(&mut self.sprite, ctx, gl);
-                }
-                EnemyState::Dying => {
-                    enemy.sprite.shatter(&mut self.sprite, self.sprite_width, self.sprite_height, ctx, gl);
-                }
-                _ => {}
-            }
-        }
+    pub fn iter(&self) -> Values<'_, Uuid, Enemy> {
+        self.enemies.values()
+    }
+
+    fn add(&mut self, kind: EnemyKind, x: f64, y: f64, now: Duration, rng: &mut GameRng) {
+        let sheet = self.sprite_sizes[kind.get_sheet()];
+        let scale = kind.get_scale();
+        let size = SpriteSize::new((sheet.width as f64 * scale) as u32, (sheet.height as f64 * scale) as u32);
+        let enemy = Enemy::new(kind, kind.new_behavior(rng), x, y, size, self.sprite_frames, now);
+        self.enemies.insert(enemy.get_sprite().get_id(), enemy);
+    }
+
+    /// Breaks a killed splitter into two fragments, side by side where it was.
+    pub fn split(&mut self, splitter: &GameSprite, now: Duration, rng: &mut GameRng) {
+        let x = splitter.x + splitter.width / 2.0;
+        let width = self.sprite_sizes[EnemyKind::Fragment.get_sheet()].width as f64 * EnemyKind::Fragment.get_scale();
+        self.add(EnemyKind::Fragment, x - width, splitter.y, now, rng);
+        self.add(EnemyKind::Fragment, x, splitter.y, now, rng);
     }
 
-    pub fn update(&mut self, game: &Game) {
+    /// Moves every enemy and spawns more. Gives the shots fired, as where from and where to.
+    pub fn update(&mut self, game: &Game, clock: &dyn Clock, rng: &mut GameRng, dt: f64) -> Vec<((f64, f64), (f64, f64))> {
+        let mut shots = Vec::new();
         match self.state {
             EnemiesState::Running => {
-                for (_, e) in self.enemies.iter_mut() {
-                    e.update(game.player, game.screen_height, game.screen_width);
+                // where every enemy was before any of them moved this tick, so the order they
+                // move in doesn't matter
+                let neighbors: Vec<(f64, f64)> = self.enemies.values()
+                    .filter(|e| e.state == EnemyState::Alive)
+                    .map(Enemy::get_center).collect();
+                let mut index = 0;
+                for e in self.enemies.values_mut() {
+                    let alive = e.state == EnemyState::Alive;
+                    if let Some(target) = e.update(game, clock, rng, &neighbors, index, dt) {
+                        shots.push((e.get_center(), target));
+                    }
+                    if alive {
+                        index += 1;
+                    }
                 }
 
-                if self.last_enemy.elapsed().unwrap().as_millis() > 500 && self.enemies.len() < MAX_ENEMIES {
-                    let i = rand::thread_rng().gen_range(0, game.black_holes.len());
+                let tuning = &game.tuning;
+                if clock.since(self.last_enemy) > tuning.get_enemy_spawn_interval() && self.enemies.len() < tuning.max_enemies {
+                    let i = rng.gen_range(0, game.black_holes.len());
                     let p = game.black_holes.get(i).unwrap();
-                    let enemy = Enemy::new(
-                        p.x as f64, p.y as f64,
-                        self.sprite_width, self.sprite_height, self.sprite_frames
-                    );
-                    self.enemies.insert(enemy.get_sprite().get_id(), enemy);
-                    self.last_enemy = SystemTime::now();
+                    let kind = match game.enemy_kinds.len() {
+                        0 => EnemyKind::Chaser,
+                        n => game.enemy_kinds[rng.gen_range(0, n)]
+                    };
+                    self.add(kind, p.x as f64, p.y as f64, clock.now(), rng);
+                    self.last_enemy = clock.now();
                 }
             }
             EnemiesState::WaitingToSpawn => {
-                if self.wait_start.elapsed().unwrap() > WAIT_TO_SPAWN_DURATION {
+                if clock.since(self.wait_start) > game.tuning.get_enemy_spawn_delay() {
                     self.state = EnemiesState::Running;
                 }
             }
             EnemiesState::WaitingForSpawnPoints => {
                 if !game.black_holes.is_empty() {
                     self.state = EnemiesState::WaitingToSpawn;
-                    self.wait_start = SystemTime::now();
+                    self.wait_start = clock.now();
                 }
             }
         }
+        shots
     }
 
     pub fn reset(&mut self) {
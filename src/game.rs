@@ -1,25 +1,66 @@
-use sdl2::rect::Rect;
-use crate::controller::Controller;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use crate::difficulty::Difficulty;
+use crate::enemy::EnemyKind;
+use crate::input::InputFrame;
+use crate::power_up::Effects;
+use crate::rect::Rect;
+use crate::tuning::Tuning;
 
-#[derive(PartialEq)]
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
 pub enum GameState {
     Starting,
     Running,
+    Paused,
     LevelComplete,
     Dying,
     Dead,
     Over
 }
 
+#[derive(Clone, Deserialize, Serialize)]
 pub struct Game {
     pub black_hole_count: u32,
     pub black_holes: Vec<Rect>,
-    pub controller: Controller,
+    pub difficulty: Difficulty,
+    /// The power-ups the player has picked up that are still going.
+    pub effects: Effects,
+    /// The kinds of enemy this level spawns. Comes from the level, so isn't saved.
+    #[serde(skip)]
+    pub enemy_kinds: Vec<EnemyKind>,
     pub high_score: u32,
+    #[serde(skip)]
+    pub input: InputFrame,
+    /// The level being played, counting from 1. Kept rather than worked out from the black
+    /// holes, so changing the tuning mid-game doesn't move the game to another level.
+    pub level: u32,
+    /// The score when the level started, which restarting it goes back to.
+    pub level_score: u32,
     pub lives: u32,
+    /// What enemies steer around: the planets that aren't being towed.
+    pub obstacles: Vec<Rect>,
     pub player: Rect,
     pub score: u32,
     pub screen_height: f64,
     pub screen_width: f64,
-    pub state: GameState
+    pub state: GameState,
+    /// The planet the player is towing, if any.
+    pub towed_planet: Option<Rect>,
+    /// When the current life on this level started, for levels with a time limit.
+    pub timer_start: Duration,
+    /// Comes from the tuning file, so isn't saved with the game.
+    #[serde(skip)]
+    pub tuning: Tuning
+}
+
+impl Game {
+    /// The level being played, counting from 1.
+    pub fn get_level(&self) -> u32 {
+        self.level
+    }
+
+    /// Whether a boss guards this level.
+    pub fn is_boss_level(&self) -> bool {
+        self.tuning.boss_levels > 0 && self.get_level().is_multiple_of(self.tuning.boss_levels)
+    }
 }
@@ -1,11 +1,19 @@
-use graphics::Context;
-use opengl_graphics::{GlGraphics, Texture};
-use sdl2::rect::Rect;
-use sprite::Sprite;
+use std::sync::atomic::{AtomicU64, Ordering};
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
+use crate::rect::Rect;
 
-#[derive(Clone, Copy, PartialEq)]
+// ids are handed out in creation order, so collections keyed by them iterate the same way every run
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+fn next_id() -> Uuid {
+    Uuid::from_u64_pair(0, NEXT_ID.fetch_add(1, Ordering::Relaxed))
+}
+
+#[derive(Clone, Copy, Deserialize, PartialEq, Serialize)]
 pub struct GameSprite {
+    // a loaded sprite gets a new id, so ids stay unique and in creation order
+    #[serde(skip, default = "next_id")]
     id: Uuid,
     shatter_x: f64,
     shatter_y: f64,
@@ -21,7 +29,7 @@ pub struct GameSprite {
 impl GameSprite {
     pub fn new(x: f64, y: f64, width: f64, height: f64) -> Self {
         Self {
-            id: Uuid::new_v4(),
+            id: next_id(),
             shatter_x: 0.0,
             shatter_y: 0.0,
             shatter_width: 0.0,
@@ -33,7 +41,7 @@ impl GameSprite {
 
     pub fn from_rect(r: &Rect) -> Self {
         Self {
-            id: Uuid::new_v4(),
+            id: next_id(),
             shatter_x: 0.0,
             shatter_y: 0.0,
             shatter_width: 0.0,
@@ -54,36 +62,21 @@ impl GameSprite {
         Rect::new(self.x as i32, self.y as i32, self.width as u32, self.height as u32)
     }
 
+    /// The area the four shattered quarters of the sprite are spread over, as `[x, y, width, height]`.
+    pub fn get_shatter_rect(&self) -> [f64; 4] {
+        [self.shatter_x, self.shatter_y, self.shatter_width, self.shatter_height]
+    }
+
     pub fn set_position(&mut self, x: f64, y: f64) -> &mut Self {
         self.x = x;
         self.y = y;
         self
     }
 
-    pub fn draw(&mut self, sprite: &mut Sprite<Texture>, ctx: Context, gl: &mut GlGraphics) {
-        sprite.set_position(self.x, self.y);
-        sprite.set_rotation(self.degrees);
-        sprite.draw(ctx.transform, gl);
-    }
-
-    pub fn shatter(&mut self, sprite: &mut Sprite<Texture>, sprite_width: u32, sprite_height: u32, ctx: Context, gl: &mut GlGraphics) {
-        let width = sprite_width as f64 / 2.0;
-        let height = sprite_height as f64 / 2.0;
-        sprite.set_src_rect([0.0, 0.0, width, height]);
-        sprite.set_position(self.shatter_x, self.shatter_y);
-        sprite.draw(ctx.transform, gl);
-
-        sprite.set_src_rect([width, 0.0, width, height]);
-        sprite.set_position(self.shatter_x + self.shatter_width - width, self.shatter_y);
-        sprite.draw(ctx.transform, gl);
-
-        sprite.set_src_rect([0.0, height, width, height]);
-        sprite.set_position(self.shatter_x + self.shatter_width - width, self.shatter_y + self.shatter_height - height);
-        sprite.draw(ctx.transform, gl);
-
-        sprite.set_src_rect([width, height, width, height]);
-        sprite.set_position(self.shatter_x, self.shatter_y + self.shatter_height - height);
-        sprite.draw(ctx.transform, gl);
+    /// Moves the sprite as little as possible to be entirely inside a `width` by `height` area.
+    pub fn keep_inside(&mut self, width: f64, height: f64) {
+        self.x = self.x.min(width - self.width).max(0.0);
+        self.y = self.y.min(height - self.height).max(0.0);
     }
 
     pub fn shatter_start(&mut self) {
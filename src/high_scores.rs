@@ -0,0 +1,139 @@
+use std::cmp::Reverse;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use crate::input::InputFrame;
+use crate::menu::StickRepeat;
+use crate::settings::{data_dir, load_toml};
+
+const HIGH_SCORES_FILE: &str = "high-scores.toml";
+pub const MAX_HIGH_SCORES: usize = 10;
+pub const INITIALS_LENGTH: usize = 3;
+const LETTERS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+
+/// Today's date in UTC, as `YYYY-MM-DD`.
+pub fn today() -> String {
+    let days = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() / 86400) as i64;
+    // days since 1970-01-01 to a civil date, from Howard Hinnant's date algorithms
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct HighScore {
+    pub initials: String,
+    pub score: u32,
+    /// The level the game ended on.
+    pub level: u32,
+    pub date: String
+}
+
+/// The best scores, highest first, kept in the data directory between runs.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct HighScores {
+    scores: Vec<HighScore>
+}
+
+impl HighScores {
+    pub fn default_path() -> Option<PathBuf> {
+        data_dir().map(|dir| dir.join(HIGH_SCORES_FILE))
+    }
+
+    /// Reads the table, which is empty when the file doesn't exist yet.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut high_scores: Self = load_toml(path)?;
+        high_scores.scores.sort_by_key(|s| Reverse(s.score));
+        high_scores.scores.truncate(MAX_HIGH_SCORES);
+        Ok(high_scores)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_toml()?)
+    }
+
+    pub fn to_toml(&self) -> io::Result<String> {
+        toml::to_string_pretty(self).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+    }
+
+    pub fn from_toml(text: &str) -> io::Result<Self> {
+        toml::from_str(text).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+    }
+
+    pub fn get_best(&self) -> u32 {
+        self.scores.first().map_or(0, |s| s.score)
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, HighScore> {
+        self.scores.iter()
+    }
+
+    /// Whether `score` would make it into the table.
+    pub fn qualifies(&self, score: u32) -> bool {
+        score > 0 && (self.scores.len() < MAX_HIGH_SCORES || self.scores.iter().any(|s| score > s.score))
+    }
+
+    /// Adds a score below any equal ones already in the table, dropping whatever falls off the end.
+    pub fn insert(&mut self, high_score: HighScore) {
+        let index = self.scores.iter().position(|s| high_score.score > s.score).unwrap_or(self.scores.len());
+        self.scores.insert(index, high_score);
+        self.scores.truncate(MAX_HIGH_SCORES);
+    }
+}
+
+/// Arcade style initials entry: up and down change the letter, left and right move between
+/// letters and confirm accepts a letter.
+#[derive(Default)]
+pub struct InitialsEntry {
+    letters: [usize; INITIALS_LENGTH],
+    position: usize,
+    stick: StickRepeat
+}
+
+impl InitialsEntry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_initials(&self) -> String {
+        self.letters.iter().map(|&l| LETTERS[l] as char).collect()
+    }
+
+    /// The letter being chosen.
+    pub fn get_position(&self) -> usize {
+        self.position
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.position >= INITIALS_LENGTH
+    }
+
+    pub fn update(&mut self, input: &InputFrame, now: Duration) {
+        if self.is_finished() {
+            return;
+        }
+
+        let (x, y) = self.stick.update(input, now);
+        // pushing up goes forwards through the alphabet
+        let letter = &mut self.letters[self.position];
+        *letter = (*letter as i32 - y).rem_euclid(LETTERS.len() as i32) as usize;
+        self.position = (self.position as i32 + x).clamp(0, INITIALS_LENGTH as i32 - 1) as usize;
+
+        if input.confirm {
+            self.position += 1;
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+use piston::Event;
+use crate::bindings::Bindings;
+use crate::controller::GamepadInput;
+use crate::difficulty::Difficulty;
+use crate::keyboard::KeyboardMouse;
+use crate::world::World;
+
+/// What the player asked for during one tick, whatever device it came from.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct InputFrame {
+    /// Movement, each axis between -1 and 1.
+    pub move_x: f64,
+    pub move_y: f64,
+    /// Direction to fire in, each axis between -1 and 1.
+    pub aim_x: f64,
+    pub aim_y: f64,
+    pub fire: bool,
+    /// Start, continue or pick a menu item.
+    pub confirm: bool,
+    pub pause: bool,
+    /// Play the next game on this difficulty.
+    pub difficulty: Option<Difficulty>
+}
+
+impl InputFrame {
+    pub fn get_move_degrees(&self) -> f64 {
+        self.move_y.atan2(self.move_x).to_degrees()
+    }
+
+    pub fn get_aim_degrees(&self) -> f64 {
+        self.aim_y.atan2(self.aim_x).to_degrees()
+    }
+
+    pub fn is_aiming(&self) -> bool {
+        self.aim_x != 0.0 || self.aim_y != 0.0
+    }
+
+    /// Combines two frames, preferring this one's sticks when it has moved them.
+    pub fn merge(self, other: InputFrame) -> InputFrame {
+        let moving = self.move_x != 0.0 || self.move_y != 0.0;
+        let aiming = self.is_aiming();
+        InputFrame {
+            move_x: if moving { self.move_x } else { other.move_x },
+            move_y: if moving { self.move_y } else { other.move_y },
+            aim_x: if aiming { self.aim_x } else { other.aim_x },
+            aim_y: if aiming { self.aim_y } else { other.aim_y },
+            fire: if aiming { self.fire } else { other.fire },
+            confirm: self.confirm || other.confirm,
+            pause: self.pause || other.pause,
+            difficulty: self.difficulty.or(other.difficulty)
+        }
+    }
+}
+
+/// Anything that can play the game: a device, a recording or a bot. The simulation asks
+/// for one frame per tick.
+pub trait InputSource {
+    /// Sees every window event, for sources driven by devices.
+    fn handle_event(&mut self, _event: &Event) {}
+
+    /// The input for the next tick, which will be applied to `world`.
+    fn next_frame(&mut self, world: &World) -> InputFrame;
+}
+
+/// A person at the machine, on a gamepad or a keyboard and mouse, whichever they touch.
+#[derive(Default)]
+pub struct LiveInput {
+    gamepad: GamepadInput,
+    keyboard: KeyboardMouse
+}
+
+impl LiveInput {
+    /// `names` maps controller ids to the names the controllers report, to pick their bindings.
+    pub fn new(bindings: Bindings, names: HashMap<u32, String>) -> Self {
+        Self {
+            gamepad: GamepadInput::new(bindings, names),
+            keyboard: KeyboardMouse::new()
+        }
+    }
+}
+
+impl InputSource for LiveInput {
+    fn handle_event(&mut self, event: &Event) {
+        self.gamepad.handle_event(event);
+        self.keyboard.handle_event(event);
+    }
+
+    fn next_frame(&mut self, world: &World) -> InputFrame {
+        let gamepad = self.gamepad.next_frame(world);
+        let keyboard = self.keyboard.next_frame(world);
+        gamepad.merge(keyboard)
+    }
+}
@@ -0,0 +1,117 @@
+use piston::{Button, Event, Key, MouseButton, MouseCursorEvent, PressEvent, ReleaseEvent};
+use crate::game::GameState;
+use crate::input::{InputFrame, InputSource};
+use crate::world::World;
+
+/// The keyboard and mouse: WASD or the arrow keys move, holding the left mouse button fires
+/// towards the cursor, Enter, Space or a click confirms and P or Esc pauses. Letting go of the left
+/// button after firing doesn't count as a click, so it can't skip past a screen by accident.
+#[derive(Default)]
+pub struct KeyboardMouse {
+    confirm: bool,
+    cursor: [f64; 2],
+    down: bool,
+    firing: bool,
+    left: bool,
+    pause: bool,
+    right: bool,
+    /// Whether the left button has fired since it was pressed.
+    shot: bool,
+    up: bool
+}
+
+impl KeyboardMouse {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn set_direction(&mut self, key: Key, pressed: bool) {
+        match key {
+            Key::W | Key::Up => self.up = pressed,
+            Key::A | Key::Left => self.left = pressed,
+            Key::S | Key::Down => self.down = pressed,
+            Key::D | Key::Right => self.right = pressed,
+            _ => {}
+        }
+    }
+
+    fn movement(&self) -> (f64, f64) {
+        let axis = |negative: bool, positive: bool| (positive as i32 - negative as i32) as f64;
+        let x = axis(self.left, self.right);
+        let y = axis(self.up, self.down);
+        if x != 0.0 && y != 0.0 {
+            // don't move faster on the diagonals
+            (x * std::f64::consts::FRAC_1_SQRT_2, y * std::f64::consts::FRAC_1_SQRT_2)
+        } else {
+            (x, y)
+        }
+    }
+
+    fn aim(&self, world: &World) -> (f64, f64) {
+        if !self.firing {
+            return (0.0, 0.0);
+        }
+
+        // from where lasers set off, which is where the ship is drawn round
+        let player = world.get_player().get_sprite().get_position();
+        let x = self.cursor[0] - player.x as f64;
+        let y = self.cursor[1] - player.y as f64;
+        let length = x.hypot(y);
+        if length == 0.0 {
+            return (0.0, 0.0);
+        }
+        (x / length, y / length)
+    }
+}
+
+impl InputSource for KeyboardMouse {
+    fn handle_event(&mut self, event: &Event) {
+        match event.press_args() {
+            Some(Button::Keyboard(key)) => self.set_direction(key, true),
+            Some(Button::Mouse(MouseButton::Left)) => {
+                self.firing = true;
+                self.shot = false;
+            }
+            _ => {}
+        }
+
+        match event.release_args() {
+            Some(Button::Keyboard(key)) => {
+                self.set_direction(key, false);
+                match key {
+                    Key::Return | Key::Space => self.confirm = true,
+                    Key::P | Key::Escape => self.pause = true,
+                    _ => {}
+                }
+            }
+            Some(Button::Mouse(MouseButton::Left)) => {
+                self.firing = false;
+                self.confirm |= !self.shot;
+            }
+            Some(Button::Mouse(_)) => self.confirm = true,
+            _ => {}
+        }
+
+        if let Some(position) = event.mouse_cursor_args() {
+            self.cursor = position;
+        }
+    }
+
+    fn next_frame(&mut self, world: &World) -> InputFrame {
+        let (move_x, move_y) = self.movement();
+        let (aim_x, aim_y) = self.aim(world);
+        if self.firing && world.get_game().state == GameState::Running {
+            self.shot = true;
+        }
+        let frame = InputFrame {
+            move_x, move_y, aim_x, aim_y,
+            fire: self.firing,
+            confirm: self.confirm,
+            pause: self.pause,
+            difficulty: None
+        };
+        self.confirm = false;
+        self.pause = false;
+        frame
+    }
+}
@@ -1,15 +1,15 @@
-use graphics::{Context, ImageSize};
-use opengl_graphics::{GlGraphics, Texture};
-use piston_window::TextureSettings;
-use sprite::Sprite;
-use std::collections::HashMap;
-use std::rc::Rc;
-use std::time::SystemTime;
-use rust_embed::EmbeddedFile;
+use std::collections::btree_map::Values;
+use std::collections::BTreeMap;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
+use crate::clock::Clock;
 use crate::game::Game;
 use crate::game_sprite::GameSprite;
+use crate::power_up::PowerUpKind;
+use crate::save::HasSprite;
 
+#[derive(Clone, Deserialize, Serialize)]
 pub struct Laser {
     m: f64,
     b: f64,
@@ -49,14 +49,15 @@ impl Laser {
         self.sprite
     }
 
-    pub fn update(&mut self) {
+    /// Moves the laser `increment` units along its major axis.
+    pub fn update(&mut self, increment: f64) {
         if self.vertical {
-            self.sprite.y += self.y_increment;
+            self.sprite.y += self.y_increment * increment;
             if self.m.abs() != f64::INFINITY {
                 self.sprite.x = (self.sprite.y - self.b) / self.m;
             }
         } else {
-            self.sprite.x += self.x_increment;
+            self.sprite.x += self.x_increment * increment;
             self.sprite.y = (self.m * self.sprite.x) + self.b;
         }
     }
@@ -74,34 +75,36 @@ impl Laser {
     }
 }
 
+impl HasSprite for Laser {
+    fn get_sprite(&self) -> GameSprite {
+        self.sprite
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
 pub struct Lasers {
-    sprite: Sprite<Texture>,
     sprite_height: u32,
     sprite_width: u32,
-    lasers: HashMap<Uuid, Laser>,
-    last_laser: SystemTime,
+    #[serde(with = "crate::save::sprite_map")]
+    lasers: BTreeMap<Uuid, Laser>,
+    last_laser: Duration,
     window_width: f64,
     window_height: f64,
 }
 
 impl Lasers {
-    pub fn new(window_width: f64, window_height: f64, sprite_file: &EmbeddedFile) -> Self {
-        let image = image::load_from_memory(sprite_file.data.as_ref()).unwrap();
-        let texture = Texture::from_image(image.as_rgba8().unwrap(), &TextureSettings::new());
-        let sprite_height = texture.get_height();
-        let sprite_width = texture.get_width();
+    pub fn new(window_width: f64, window_height: f64, sprite_width: u32, sprite_height: u32) -> Self {
         Self {
-            sprite: Sprite::from_texture(Rc::new(texture)),
             sprite_height,
             sprite_width,
-            lasers: HashMap::new(),
-            last_laser: SystemTime::now(),
+            lasers: BTreeMap::new(),
+            last_laser: Duration::ZERO,
             window_width,
             window_height
         }
     }
 
-    pub fn get_lasers(&mut self) -> &HashMap<Uuid, Laser> {
+    pub fn get_lasers(&mut self) -> &BTreeMap<Uuid, Laser> {
         &self.lasers
     }
 
@@ -109,27 +112,36 @@ impl Lasers {
         self.lasers.remove(id);
     }
 
-    pub fn draw(&mut self, ctx: Context, gl: &mut GlGraphics) {
-        for laser in self.lasers.values() {
-            self.sprite.set_position(laser.sprite.x, laser.sprite.y);
-            self.sprite.set_rotation(laser.sprite.degrees);
-            self.sprite.draw(ctx.transform, gl);
-        }
+    pub fn iter(&self) -> Values<'_, Uuid, Laser> {
+        self.lasers.values()
     }
 
-    pub fn update(&mut self, game: &Game) {
-        let right_stick_pos = game.controller.get_right_stick();
-        if (right_stick_pos.get_x() != 0.0 || right_stick_pos.get_y() != 0.0) && (self.lasers.len() <= 10 && self.last_laser.elapsed().unwrap().as_millis() > 100) {
+    pub fn update(&mut self, game: &Game, clock: &dyn Clock, dt: f64) {
+        let input = game.input;
+        let tuning = &game.tuning;
+        let (max_lasers, fire_delay) = if game.effects.is_active(PowerUpKind::RapidFire) {
+            (usize::MAX, tuning.get_rapid_fire_delay())
+        } else {
+            (tuning.max_lasers, tuning.get_laser_fire_delay())
+        };
+        if (input.fire && input.is_aiming()) && (self.lasers.len() < max_lasers && clock.since(self.last_laser) > fire_delay) {
             let player_x = game.player.x as f64;
             let player_y = game.player.y as f64;
-            let laser = Laser::new(
-                right_stick_pos.get_degrees(), player_x, player_y,
-                player_x + (right_stick_pos.get_screen_x() - (self.window_width / 2.0)),
-                player_y + (right_stick_pos.get_screen_y() - (self.window_height / 2.0)),
-                self.sprite_width, self.sprite_height
-            );
-            self.lasers.insert(laser.sprite.get_id(), laser);
-            self.last_laser = SystemTime::now();
+            // spread shot adds a laser either side of the aim, as many as the cap leaves room for
+            let spread = if game.effects.is_active(PowerUpKind::Spread) { tuning.spread_degrees } else { 0.0 };
+            let offsets = if spread == 0.0 { vec![0.0] } else { vec![0.0, -spread, spread] };
+            let room = max_lasers - self.lasers.len();
+            for offset in offsets.into_iter().take(room) {
+                let degrees = input.get_aim_degrees() + offset;
+                let (dy, dx) = degrees.to_radians().sin_cos();
+                let laser = Laser::new(
+                    degrees, player_x, player_y,
+                    player_x + dx, player_y + dy,
+                    self.sprite_width, self.sprite_height
+                );
+                self.lasers.insert(laser.sprite.get_id(), laser);
+            }
+            self.last_laser = clock.now();
         }
 
         let mut to_remove = vec!();
@@ -137,7 +149,7 @@ impl Lasers {
             if laser.is_off_screen(self.window_width, self.window_height) {
                 to_remove.push(laser.sprite.get_id());
             } else {
-                laser.update();
+                laser.update(tuning.laser_speed * dt);
             }
         }
         for id in to_remove.iter() {
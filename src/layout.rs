@@ -0,0 +1,117 @@
+use rand::Rng;
+use crate::planets::PLANET_FRAMES;
+use crate::rect::Rect;
+use crate::rng::GameRng;
+use crate::world::SpriteSize;
+
+// the most room kept between any two items, and around the player
+const MAX_SPACING: u32 = 96;
+// tries at each spacing before settling for less room
+const ATTEMPTS: u32 = 200;
+
+/// Where a level's black holes and planets go, in playfield units. Planets carry their sprite.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Layout {
+    pub black_holes: Vec<Rect>,
+    pub planets: Vec<(Rect, u32)>
+}
+
+/// Lays out `count` black holes and as many planets inside the playfield, clear of each other and
+/// of the player. The room between items shrinks as there are more of them, and when the
+/// playfield is too crowded for any room at all items may touch, so this always finishes.
+pub fn generate(
+    rng: &mut GameRng, count: u32, black_hole: SpriteSize, planet: SpriteSize,
+    playfield: (f64, f64), player: Rect
+) -> Layout {
+    let spacing = spacing_for(count * 2, black_hole.width.max(planet.width), playfield);
+    let mut taken = vec![player.inflate(spacing)];
+    let mut layout = Layout::default();
+    for _ in 0..count {
+        let r = place(rng, black_hole, playfield, &taken, spacing);
+        taken.push(r);
+        layout.black_holes.push(r);
+    }
+    for i in 0..count {
+        let r = place(rng, planet, playfield, &taken, spacing);
+        taken.push(r);
+        layout.planets.push((r, i.rem_euclid(PLANET_FRAMES)));
+    }
+    layout
+}
+
+// as much room as `items` of about `size` leave when spread evenly, within MAX_SPACING
+fn spacing_for(items: u32, size: u32, (width, height): (f64, f64)) -> u32 {
+    // one more share for the player
+    let share = (width * height / (items + 1) as f64).sqrt();
+    ((share - size as f64) / 2.0).clamp(0.0, MAX_SPACING as f64) as u32
+}
+
+// a spot inside the playfield at least `spacing` from everything taken, halving the spacing
+// whenever it can't find one. The first rect taken is the player's, kept clear to the last.
+fn place(rng: &mut GameRng, size: SpriteSize, playfield: (f64, f64), taken: &[Rect], spacing: u32) -> Rect {
+    let max_x = (playfield.0 as i32 - size.width as i32).max(0);
+    let max_y = (playfield.1 as i32 - size.height as i32).max(0);
+    let mut spacing = spacing;
+    let mut fallback = None;
+    loop {
+        for _ in 0..ATTEMPTS {
+            let r = Rect::new(rng.gen_range(0, max_x + 1), rng.gen_range(0, max_y + 1), size.width, size.height);
+            let room = r.inflate(spacing);
+            if !taken.iter().any(|t| room.has_intersection(*t)) {
+                return r;
+            }
+            if fallback.is_none() || !taken.first().is_some_and(|player| r.has_intersection(*player)) {
+                fallback = Some(r);
+            }
+        }
+        if spacing == 0 {
+            // too crowded to keep everything apart
+            return fallback.unwrap_or(Rect::new(0, 0, size.width, size.height));
+        }
+        spacing /= 2;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::layout_rng;
+
+    const SIZE: SpriteSize = SpriteSize { width: 64, height: 64 };
+
+    fn every_rect(layout: &Layout) -> Vec<Rect> {
+        layout.black_holes.iter().copied().chain(layout.planets.iter().map(|(r, _)| *r)).collect()
+    }
+
+    fn inside(r: Rect, (width, height): (f64, f64)) -> bool {
+        r.x >= 0 && r.y >= 0 && (r.x + r.w as i32) as f64 <= width && (r.y + r.h as i32) as f64 <= height
+    }
+
+    #[test]
+    fn items_stay_inside_apart_and_clear_of_the_player() {
+        let playfield = (1600.0, 900.0);
+        let player = Rect::new(768, 418, 64, 64);
+        for seed in 0..20 {
+            for count in 3..=8 {
+                let layout = generate(&mut layout_rng(seed, count), count, SIZE, SIZE, playfield, player);
+                let rects = every_rect(&layout);
+                assert_eq!((layout.black_holes.len(), layout.planets.len()), (count as usize, count as usize));
+                let clear_of_player = player.inflate(spacing_for(count * 2, SIZE.width, playfield));
+                for (i, r) in rects.iter().enumerate() {
+                    assert!(inside(*r, playfield), "{:?} is outside the playfield", r);
+                    assert!(!r.has_intersection(clear_of_player), "{:?} is too close to the player", r);
+                    assert!(rects[i + 1..].iter().all(|other| !r.has_intersection(*other)), "{:?} overlaps", r);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn a_crowded_playfield_still_gets_every_item() {
+        let playfield = (200.0, 150.0);
+        let player = Rect::new(68, 43, 64, 64);
+        let layout = generate(&mut layout_rng(1, 12), 12, SIZE, SIZE, playfield, player);
+        assert_eq!((layout.black_holes.len(), layout.planets.len()), (12, 12));
+        assert!(every_rect(&layout).iter().all(|r| inside(*r, playfield)));
+    }
+}
@@ -0,0 +1,201 @@
+use std::io::{self, ErrorKind};
+use std::path::Path;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use crate::enemy::EnemyKind;
+use crate::layout::Layout;
+use crate::planets::PLANET_FRAMES;
+use crate::rect::Rect;
+use crate::settings::load_toml;
+use crate::tuning::Tuning;
+use crate::world::SpriteSize;
+
+// the campaign the game ships with
+const BUILT_IN_LEVELS: &str = include_str!("../assets/levels.toml");
+
+/// A planet placed by hand. Positions are fractions of the playfield, from 0 at the top left to
+/// 1 at the bottom right, so a level fits whatever shape the window is.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct LevelPlanet {
+    pub x: f64,
+    pub y: f64,
+    /// Which of the planet sprites to draw.
+    #[serde(default)]
+    pub sprite: u32
+}
+
+/// How enemies spawn on a level. Anything left out comes from the tuning.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct EnemyRules {
+    pub max_enemies: Option<usize>,
+    pub speed: Option<f64>,
+    pub spawn_delay_millis: Option<u64>,
+    pub spawn_interval_millis: Option<u64>,
+    /// The kinds of enemy that spawn, in place of the ones the level number brings in.
+    pub kinds: Option<Vec<EnemyKind>>
+}
+
+impl EnemyRules {
+    /// The tuning with these rules in place of its enemy values.
+    pub fn apply(&self, tuning: &Tuning) -> Tuning {
+        let mut tuning = tuning.clone();
+        tuning.max_enemies = self.max_enemies.unwrap_or(tuning.max_enemies);
+        tuning.enemy_speed = self.speed.unwrap_or(tuning.enemy_speed);
+        tuning.enemy_spawn_delay_millis = self.spawn_delay_millis.unwrap_or(tuning.enemy_spawn_delay_millis);
+        tuning.enemy_spawn_interval_millis = self.spawn_interval_millis.unwrap_or(tuning.enemy_spawn_interval_millis);
+        tuning
+    }
+}
+
+/// A hand-authored level. Black holes are `[x, y]` fractions of the playfield, like planets.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct Level {
+    pub name: String,
+    pub black_holes: Vec<[f64; 2]>,
+    pub planets: Vec<LevelPlanet>,
+    pub enemies: EnemyRules,
+    /// Seconds each life has to cover every black hole.
+    pub time_limit_secs: Option<u64>
+}
+
+impl Level {
+    pub fn get_time_limit(&self) -> Option<Duration> {
+        self.time_limit_secs.map(Duration::from_secs)
+    }
+
+    /// Where this level's items go on a playfield of this size.
+    pub fn to_layout(&self, black_hole: SpriteSize, planet: SpriteSize, playfield: (f64, f64)) -> Layout {
+        let to_rect = |x: f64, y: f64, size: SpriteSize| {
+            let x = x * (playfield.0 - size.width as f64).max(0.0);
+            let y = y * (playfield.1 - size.height as f64).max(0.0);
+            Rect::new(x as i32, y as i32, size.width, size.height)
+        };
+        Layout {
+            black_holes: self.black_holes.iter().map(|&[x, y]| to_rect(x, y, black_hole)).collect(),
+            planets: self.planets.iter().map(|p| (to_rect(p.x, p.y, planet), p.sprite)).collect()
+        }
+    }
+
+    fn validate(&self, number: usize) -> io::Result<()> {
+        let invalid = |message: &str| Err(io::Error::new(ErrorKind::InvalidData, format!("level {}: {}", number, message)));
+        let in_bounds = |x: f64, y: f64| (0.0..=1.0).contains(&x) && (0.0..=1.0).contains(&y);
+        if self.black_holes.is_empty() {
+            return invalid("needs at least one black hole");
+        }
+        if self.planets.len() < self.black_holes.len() {
+            return invalid("needs a planet for every black hole");
+        }
+        if !self.black_holes.iter().all(|&[x, y]| in_bounds(x, y)) || !self.planets.iter().all(|p| in_bounds(p.x, p.y)) {
+            return invalid("positions must be between 0 and 1");
+        }
+        if self.planets.iter().any(|p| p.sprite >= PLANET_FRAMES) {
+            return invalid(&format!("planet sprites go from 0 to {}", PLANET_FRAMES - 1));
+        }
+        if let Some(kinds) = &self.enemies.kinds {
+            if kinds.is_empty() {
+                return invalid("needs at least one kind of enemy");
+            }
+            if kinds.contains(&EnemyKind::Fragment) {
+                return invalid("fragments only come from splitters");
+            }
+        }
+        if self.time_limit_secs == Some(0) {
+            return invalid("the time limit can't be zero");
+        }
+        Ok(())
+    }
+}
+
+/// The authored levels, played in order before the procedural ones take over.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct Campaign {
+    levels: Vec<Level>
+}
+
+impl Campaign {
+    /// The levels that come with the game.
+    pub fn built_in() -> Self {
+        Self::from_toml(BUILT_IN_LEVELS).expect("the built-in levels are valid")
+    }
+
+    /// Reads a campaign, which is empty, leaving every level procedural, when the file doesn't
+    /// exist.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let campaign: Self = load_toml(path)?;
+        campaign.validate()?;
+        Ok(campaign)
+    }
+
+    pub fn from_toml(text: &str) -> io::Result<Self> {
+        let campaign: Self = toml::from_str(text).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+        campaign.validate()?;
+        Ok(campaign)
+    }
+
+    /// The authored level `number`, counting from 1, if the campaign goes that far.
+    pub fn get_level(&self, number: u32) -> Option<&Level> {
+        self.levels.get((number as usize).checked_sub(1)?)
+    }
+
+    pub fn len(&self) -> usize {
+        self.levels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels.is_empty()
+    }
+
+    fn validate(&self) -> io::Result<()> {
+        self.levels.iter().enumerate().try_for_each(|(i, level)| level.validate(i + 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEVEL: &str = r#"
+[[levels]]
+name = "Test"
+black_holes = [[0.1, 0.2]]
+planets = [{ x = 0.5, y = 0.5, sprite = 1 }]
+enemies = { max_enemies = 5, kinds = ["chaser"] }
+time_limit_secs = 30
+"#;
+
+    fn turned_away(text: &str) -> bool {
+        Campaign::from_toml(text).err().map(|e| e.kind()) == Some(ErrorKind::InvalidData)
+    }
+
+    #[test]
+    fn campaigns_read_back_as_written() {
+        let campaign = Campaign::built_in();
+        assert!(!campaign.is_empty());
+        let text = toml::to_string(&campaign).unwrap();
+        assert_eq!(Campaign::from_toml(&text).unwrap(), campaign);
+
+        let campaign = Campaign::from_toml(LEVEL).unwrap();
+        let level = campaign.get_level(1).unwrap();
+        assert_eq!(level.black_holes, vec![[0.1, 0.2]]);
+        assert_eq!(level.planets, vec![LevelPlanet { x: 0.5, y: 0.5, sprite: 1 }]);
+        assert_eq!(level.enemies.kinds, Some(vec![EnemyKind::Chaser]));
+        assert_eq!(level.get_time_limit(), Some(Duration::from_secs(30)));
+        assert!(campaign.get_level(0).is_none() && campaign.get_level(2).is_none());
+        assert_eq!(Campaign::from_toml(&toml::to_string(&campaign).unwrap()).unwrap(), campaign);
+    }
+
+    #[test]
+    fn unplayable_levels_are_turned_away() {
+        assert!(turned_away(&LEVEL.replace("[[0.1, 0.2]]", "[]")));
+        assert!(turned_away(&LEVEL.replace("[[0.1, 0.2]]", "[[0.1, 0.2], [0.9, 0.2]]")));
+        assert!(turned_away(&LEVEL.replace("x = 0.5", "x = 1.5")));
+        assert!(turned_away(&LEVEL.replace("sprite = 1", &format!("sprite = {}", PLANET_FRAMES))));
+        assert!(turned_away(&LEVEL.replace("[\"chaser\"]", "[]")));
+        assert!(turned_away(&LEVEL.replace("\"chaser\"", "\"fragment\"")));
+        assert!(turned_away(&LEVEL.replace("30", "0")));
+        assert!(turned_away("levels = 3"));
+    }
+}
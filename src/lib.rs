@@ -0,0 +1,32 @@
+pub mod behavior;
+pub mod bindings;
+pub mod black_hole;
+pub mod boss;
+pub mod bot;
+pub mod clock;
+pub mod controller;
+pub mod difficulty;
+pub mod enemy;
+pub mod game;
+pub mod game_sprite;
+pub mod high_scores;
+pub mod input;
+pub mod keyboard;
+pub mod laser;
+pub mod layout;
+pub mod levels;
+pub mod menu;
+pub mod planets;
+pub mod player;
+pub mod power_up;
+pub mod projectile;
+pub mod rect;
+pub mod replay;
+pub mod rng;
+pub mod save;
+pub mod settings;
+pub mod steering;
+pub mod timestep;
+pub mod tuning;
+pub mod viewport;
+pub mod world;
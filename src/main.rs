@@ -1,11 +1,6 @@
-mod controller;
-mod laser;
-mod player;
-mod enemy;
-mod black_hole;
-mod planets;
-mod game_sprite;
-mod game;
+mod options;
+mod renderer;
+mod scenes;
 
 extern crate graphics;
 extern crate image;
@@ -15,274 +10,247 @@ extern crate sdl2_window;
 extern crate sprite;
 extern crate rust_embed;
 
-use graphics::{clear, text, Transformed};
-use opengl_graphics::{GlGraphics, GlyphCache, OpenGL, ImageSize, Texture};
-use piston::{Button, ControllerAxisEvent, Events, EventSettings, RenderEvent, ReleaseEvent};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+use clap::Parser;
+use opengl_graphics::{GlGraphics, GlyphCache, OpenGL};
+use piston::{Button, Event, Events, EventLoop, EventSettings, Input, Key, Motion, ReleaseEvent, RenderEvent, ResizeEvent, UpdateEvent};
 use piston::window::WindowSettings;
-use piston_window::{color, TextureSettings, Window};
+use piston_window::{TextureSettings, Window};
+use planet_wrangler::clock::{Clock, SystemClock};
+use planet_wrangler::bot::BotInput;
+use planet_wrangler::game::GameState;
+use planet_wrangler::high_scores::{today, HighScores};
+use planet_wrangler::input::{InputSource, LiveInput};
+use planet_wrangler::levels::Campaign;
+use planet_wrangler::replay::{Recorder, Replay};
+use planet_wrangler::rng::random_seed;
+use planet_wrangler::save::SaveGame;
+use planet_wrangler::settings::{DisplayMode, Settings};
+use planet_wrangler::tuning::Tuning;
+use planet_wrangler::viewport::{Viewport, VIEW_HEIGHT, VIEW_WIDTH};
+use planet_wrangler::timestep::{FixedTimestep, TICKS_PER_SECOND};
+use planet_wrangler::world::World;
 use rust_embed::RustEmbed;
+use sdl2::video::FullscreenType;
 use sdl2_window::Sdl2Window;
-use uuid::Uuid;
-use crate::black_hole::{BlackHole, BlackHoles, BlackHoleState};
-use crate::controller::Controller;
-use crate::enemy::{Enemies, EnemyState};
-use crate::game::{Game, GameState};
-use crate::laser::Lasers;
-use crate::planets::{Planets, PlanetState};
-use crate::player::{Player, PlayerState};
+use crate::options::Options;
+use crate::renderer::Renderer;
+use crate::scenes::{first_scene, App, Transition};
 
 const SCORE_HEIGHT: f64 = 20.0;
-const WINDOW_HEIGHT: f64 = 1000.0;
-const WINDOW_WIDTH: f64 = 1000.0;
 
 #[derive(RustEmbed)]
 #[folder = "assets/"]
 struct Assets;
 
-fn update_score(score: u32, high_score: u32, increment: u32) -> (u32, u32) {
-    let new_score = score + increment;
-    let new_high_score = high_score.max(new_score);
-    (new_score, new_high_score)
+/// Reads one of the game's files, falling back to the defaults if it can't be read.
+fn load_or_default<T: Default>(path: Option<&Path>, what: &str, load: impl Fn(&Path) -> io::Result<T>) -> T {
+    match path.map(load) {
+        Some(Ok(value)) => value,
+        Some(Err(error)) => {
+            eprintln!("could not read {}, using the defaults: {}", what, error);
+            T::default()
+        }
+        None => T::default()
+    }
+}
+
+/// Moves mouse positions from the window into the logical view.
+fn to_logical(event: Event, viewport: &Viewport) -> Event {
+    match event {
+        Event::Input(Input::Move(Motion::MouseCursor(position)), timestamp) => {
+            Event::Input(Input::Move(Motion::MouseCursor(viewport.to_logical(position))), timestamp)
+        }
+        event => event
+    }
+}
+
+/// The name of every connected controller, keyed by the id its events carry.
+fn controller_names(window: &Sdl2Window) -> HashMap<u32, String> {
+    let mut names = HashMap::new();
+    if let Ok(joysticks) = window.sdl_context.joystick() {
+        for index in 0..joysticks.num_joysticks().unwrap_or(0) {
+            if let Ok(joystick) = joysticks.open(index) {
+                names.insert(joystick.instance_id(), joystick.name());
+            }
+        }
+    }
+    names
 }
 
 fn main() {
+    let options = Options::parse();
+    let replay = options.replay.as_ref().map(|path| {
+        Replay::read(BufReader::new(File::open(path).expect("could not open recording"))).expect("could not read recording")
+    });
+    let seed = match &replay {
+        Some(replay) => replay.get_seed(),
+        None => options.seed.unwrap_or_else(random_seed)
+    };
+    let settings_path = options.settings.clone().or_else(Settings::default_path);
+    let settings = load_or_default(settings_path.as_deref(), "settings", Settings::load);
+    let tuning_path = options.tuning.clone().or_else(Tuning::default_path);
+    let tuning = load_or_default(tuning_path.as_deref(), "tuning", Tuning::load);
+    let campaign = match options.levels.as_deref() {
+        Some(path) => load_or_default(Some(path), "levels", Campaign::load),
+        None => Campaign::built_in()
+    };
+    // a replay plays against the table it was recorded with, and never changes the saved one
+    let high_scores_path = HighScores::default_path().filter(|_| replay.is_none());
+    let high_scores = match &replay {
+        Some(replay) => replay.get_high_scores().clone(),
+        None => load_or_default(high_scores_path.as_deref(), "high scores", HighScores::load)
+    };
+    let recorder = options.record.as_ref().map(|path| {
+        let file = BufWriter::new(File::create(path).expect("could not create recording"));
+        Recorder::new(file, seed, &high_scores).expect("could not write recording")
+    });
+
+    let mut display = settings.display.clone();
+    options.apply_display(&mut display);
+
     let opengl = OpenGL::V3_2;
-    let mut window: Sdl2Window = WindowSettings::new("Planet Wrangler", [WINDOW_WIDTH, WINDOW_HEIGHT])
-        .exit_on_esc(true)
+    let mut window: Sdl2Window = WindowSettings::new("Planet Wrangler", [display.width, display.height])
+        .exit_on_esc(false)
         .graphics_api(opengl)
-        .fullscreen(true)
+        .fullscreen(display.mode == DisplayMode::Fullscreen)
+        .decorated(display.mode != DisplayMode::Borderless)
+        .vsync(display.vsync)
         .build()
         .unwrap();
+    if display.mode == DisplayMode::Borderless {
+        window.window.set_fullscreen(FullscreenType::Desktop).unwrap();
+    }
+
+    let names = controller_names(&window);
+    let live = replay.is_none() && !options.bot;
+    let input: Box<dyn InputSource> = match replay {
+        Some(replay) => Box::new(replay),
+        None if options.bot => Box::new(BotInput::new()),
+        None => Box::new(LiveInput::new(settings.bindings.clone(), names.clone()))
+    };
 
     let mut gl = GlGraphics::new(OpenGL::V3_2);
 
     let font = Assets::get("PressStart2PRegular.ttf").unwrap();
-    let hero_png = Assets::get("hero.png").unwrap();
-    let image = image::load_from_memory(hero_png.data.as_ref()).unwrap();
-    let texture = Texture::from_image(image.as_rgba8().unwrap(), &TextureSettings::new());
-    let size = texture.get_size();
-
-    let window_width = window.size().width;
-    let window_height = window.size().height;
-    let game_height = window_height - SCORE_HEIGHT - size.1 as f64;
-
     let mut glyphs = GlyphCache::from_bytes(font.data.as_ref(), (), TextureSettings::new()).unwrap();
 
-    let mut black_holes = BlackHoles::new(&Assets::get("black-hole.png").unwrap());
-    let mut enemies = Enemies::new(&Assets::get("enemy.png").unwrap(), 3);
-    let mut lasers = Lasers::new(window_width, game_height, &Assets::get("laser.png").unwrap());
-    let mut planets = Planets::new(
-        &Assets::get("done.png").unwrap(),
-        &Assets::get("planets.png").unwrap(),
-        3, window_width, game_height
-    );
-    let mut player = Player::new(window_width, game_height, &hero_png);
+    let mut renderer = Renderer::new(|name| Assets::get(name).unwrap());
+    let sizes = renderer.get_sprite_sizes();
 
-    let mut game = Game{
-        black_hole_count: 3,
-        black_holes: Vec::new(),
-        controller: Controller::new(window_width, window_height),
-        high_score: 0,
-        lives: 3,
-        player: player.get_sprite().get_position(),
-        score: 0,
-        screen_height: game_height,
-        screen_width: window_width,
-        state: GameState::Starting
+    // the playfield is the same size on every window, which only changes how it's scaled
+    let game_height = VIEW_HEIGHT - SCORE_HEIGHT - sizes.player.height as f64;
+    let mut world = World::new(VIEW_WIDTH, game_height, sizes, tuning.clone(), seed);
+    world.set_campaign(campaign.clone());
+    world.set_high_scores(high_scores.clone());
+    world.set_date(today());
+    let mut viewport = Viewport::fit(window.size().width, window.size().height);
+    // only people playing save, and a recording can't start from a save
+    let save_path = SaveGame::default_path().filter(|_| live);
+    let has_save = save_path.as_deref().is_some_and(|path| path.exists()) && recorder.is_none();
+
+    let mut app = App {
+        campaign,
+        continued_from_save: false,
+        has_save,
+        high_scores,
+        high_scores_path,
+        input,
+        live,
+        names,
+        recorder,
+        save_path,
+        settings,
+        settings_path,
+        start_pending: false,
+        tuning,
+        world
     };
+    let mut scenes = vec![first_scene(&app)];
 
-    let mut events = Events::new(EventSettings::new());
+    let clock = SystemClock::new();
+    let mut last_update = clock.now();
+    let mut timestep = FixedTimestep::new();
+    let mut events = Events::new(EventSettings::new().ups(TICKS_PER_SECOND as u64).max_fps(display.fps));
     while let Some(event) = events.next(&mut window) {
-        if let Some(args) = event.controller_axis_args() {
-            game.controller.update(args);
-        }
-
-        if let Some(Button::Controller(_)) = event.release_args() {
-            if game.state != GameState::Running {
-                if game.state == GameState::Over {
-                    game.lives = 3;
-                    game.black_hole_count = 3;
-                    game.score = 0;
-                } else if game.state == GameState::LevelComplete {
-                    game.black_hole_count += 1;
-                }
-
-                player.reset();
-                for (_, planet) in planets.get_planets().iter_mut() {
-                    if planet.get_state() == PlanetState::Towed {
-                        planet.not_towed();
+        let event = to_logical(event, &viewport);
+        if options.dev && event.release_args() == Some(Button::Keyboard(Key::F5)) {
+            if let Some(path) = tuning_path.as_deref() {
+                match Tuning::load(path) {
+                    Ok(loaded) => {
+                        app.tuning = loaded;
+                        app.world.set_tuning(app.tuning.clone());
+                        eprintln!("reloaded {}", path.display());
                     }
+                    Err(error) => eprintln!("could not reload tuning: {}", error)
                 }
-                game.state = GameState::Running;
             }
         }
 
-        match game.state {
-            GameState::Starting | GameState::Over | GameState::LevelComplete => {
-                black_holes.reset();
-                enemies.reset();
-                lasers.reset();
-                planets.reset();
-                player.reset();
-            }
-            GameState::Dying => {
-                player.update(&game);
-                if player.get_state() == PlayerState::Dead {
-                    if game.lives == 0 {
-                        game.state = GameState::Over;
-                    } else {
-                        game.state = GameState::Dead;
-                    }
-                }
-            }
-            GameState::Dead => {
-                enemies.reset();
-                lasers.reset();
-            }
-            GameState::Running => {
-                game.player = player.get_sprite().get_position();
-                game.black_holes = black_holes.get_black_holes().iter()
-                    .filter(|h|h.get_state() == BlackHoleState::Open)
-                    .map(|h|h.get_sprite().get_position()).collect();
-
-                player.update(&game);
-                black_holes.update(&game);
-                planets.update(&game);
-                enemies.update(&game);
-                player.update(&game);
-                lasers.update(&game);
-
-                let pr = player.get_sprite().get_position();
-                let mut enemies_to_remove: Vec<Uuid> = vec![];
-                let mut lasers_to_remove: Vec<Uuid> = vec![];
-                for (ei, e) in enemies.get_enemies().iter_mut() {
-                    if e.get_state() == EnemyState::Dying {
-                        continue;
-                    }
-
-                    if e.get_state() == EnemyState::Dead {
-                        enemies_to_remove.push( *ei);
-                        continue;
-                    }
-
-                    let er = e.get_sprite().get_position();
-                    if er.has_intersection(pr) {
-                        enemies_to_remove.push( *ei);
-                        game.lives -= 1;
-                        player.dying();
-                        game.state = GameState::Dying;
-                        continue;
-                    }
-
-                    for (li, l) in lasers.get_lasers().iter() {
-                        let lr = l.get_sprite().get_position();
-                        if lr.has_intersection(er) {
-                            e.dying();
-                            lasers_to_remove.push( * li);
-                            (game.score, game.high_score) = update_score(game.score, game.high_score, 10);
-                        }
-                    }
-                }
-
-                if player.get_state() == PlayerState::NotTowing {
-                    for (_, planet) in planets.get_planets().iter_mut() {
-                        if (planet.get_state() == PlanetState::NotTowed) && (planet.get_sprite().get_position().has_intersection(pr)) {
-                            player.towing();
-                            planet.towed();
-                            break;
-                        }
-                    }
-                }
-
-                for (_, planet) in planets.get_planets().iter_mut() {
-                    if planet.get_state() == PlanetState::Towed {
-                        for black_hole in black_holes.get_black_holes().iter_mut() {
-                            if (black_hole.get_state() == BlackHoleState::Open) && (black_hole.get_sprite().get_position().has_intersection(planet.get_sprite().get_position())) {
-                                black_hole.covered();
-                                planet.in_place(black_hole.get_sprite().get_position());
-                                player.not_towing();
-                                (game.score, game.high_score) = update_score(game.score, game.high_score, 100);
-                                break;
-                            }
-                        }
-                    }
-                }
-
-                for e in enemies_to_remove.iter() {
-                    enemies.remove(e);
-                }
+        if let Some(args) = event.resize_args() {
+            viewport = Viewport::fit(args.window_size[0], args.window_size[1]);
+        }
 
-                for l in lasers_to_remove.iter() {
-                    lasers.remove(l);
+        let Some(scene) = scenes.last_mut() else { break };
+        let mut transition = scene.handle_event(&event, &mut app);
+        // a scene that's leaving doesn't get any more ticks
+        if event.update_args().is_some() {
+            let elapsed = clock.since(last_update);
+            last_update += elapsed;
+            for _ in 0..timestep.advance(elapsed.as_secs_f64()) {
+                if !matches!(transition, Transition::None) {
+                    break;
                 }
-
-                if !black_holes.get_black_holes().is_empty() {
-                    let open_black_holes: Vec<&BlackHole> = black_holes.get_black_holes().iter().filter(|h|h.get_state() == BlackHoleState::Open).collect();
-                    if open_black_holes.is_empty() {
-                        game.state = GameState::LevelComplete;
-                    }
+                if let Some(scene) = scenes.last_mut() {
+                    transition = scene.tick(&mut app, last_update);
                 }
             }
         }
+        match transition {
+            Transition::None => {}
+            Transition::Push(scene) => scenes.push(scene),
+            Transition::Pop => { scenes.pop(); }
+            Transition::Replace(scene) => {
+                scenes.pop();
+                scenes.push(scene);
+            }
+            Transition::Quit => scenes.clear()
+        }
+        if scenes.is_empty() {
+            break;
+        }
 
         if let Some(args) = event.render_args() {
-            gl.draw(args.viewport(), |ctx, gl| {
-                clear(color::BLACK, gl);
-                let y = window_height - 30.0;
-                let mut transform = ctx.transform.trans(48.0, y);
-                text::Text::new_color(color::YELLOW, 24).draw(
-                    &format!("{}", game.score), &mut glyphs, &ctx.draw_state, transform, gl
-                ).unwrap();
+            if let Some(scene) = scenes.last_mut() {
+                gl.draw(args.viewport(), |ctx, gl| scene.draw(&app, &mut renderer, &viewport, &mut glyphs, ctx, gl));
+            }
+        }
+    }
 
-                transform = ctx.transform.trans((window_width / 2.0) - (24.0 * 4.0), y);
-                text::Text::new_color(color::YELLOW, 24).draw(
-                    &format!("High: {}", game.high_score), &mut glyphs, &ctx.draw_state, transform, gl
-                ).unwrap();
+    // a high score whose initials weren't finished still counts
+    app.world.finish_initials();
+    app.keep_high_scores();
 
-                transform = ctx.transform.trans(window_width - 72.0, y);
-                text::Text::new_color(color::YELLOW, 24).draw(
-                    &format!("{}", game.lives), &mut glyphs, &ctx.draw_state, transform, gl
-                ).unwrap();
+    if let Some(path) = app.save_path.as_deref() {
+        let saved = if app.world.is_in_progress() {
+            app.world.to_save().save(path)
+        } else if app.continued_from_save && app.world.get_game().state == GameState::Over {
+            // only the saved game ending removes the save, not a new game started alongside it
+            SaveGame::remove(path)
+        } else {
+            Ok(())
+        };
+        if let Err(error) = saved {
+            eprintln!("could not save the game: {}", error);
+        }
+    }
 
-                match game.state {
-                    GameState::Starting | GameState::Over => {
-                        if game.state == GameState::Starting {
-                            let transform = ctx.transform.trans((window_width / 2.0) - 250.0, (game_height / 2.0) - 14.0);
-                            text::Text::new_color(color::YELLOW, 14).draw(
-                                "Press a button to start",
-                                &mut glyphs, &ctx.draw_state, transform, gl
-                            ).unwrap();
-                        } else {
-                            let transform = ctx.transform.trans((window_width / 2.0) - 300.0, (game_height / 2.0) - 14.0);
-                            text::Text::new_color(color::YELLOW, 14).draw(
-                                "Game Over. Press any key to play again",
-                                &mut glyphs, &ctx.draw_state, transform, gl
-                            ).unwrap();
-                        }
-                    }
-                    GameState::Dead => {
-                        let transform = ctx.transform.trans((window_width / 2.0) - 300.0, (game_height / 2.0) - 14.0);
-                        text::Text::new_color(color::YELLOW, 14).draw(
-                            "Got you. Press any key to continue",
-                            &mut glyphs, &ctx.draw_state, transform, gl
-                        ).unwrap();
-                    }
-                    GameState::LevelComplete => {
-                        let transform = ctx.transform.trans((window_width / 2.0) - 370.0, (game_height / 2.0) - 14.0);
-                        text::Text::new_color(color::YELLOW, 14).draw(
-                            "Level complete. Press any key to continue",
-                            &mut glyphs, &ctx.draw_state, transform, gl
-                        ).unwrap();
-                    }
-                    _ => {
-                        black_holes.draw(ctx, gl);
-                        planets.draw(ctx, gl);
-                        player.draw(ctx, gl);
-                        lasers.draw(ctx, gl);
-                        enemies.draw(ctx, gl);
-                    }
-                }
-            });
+    if let Some(mut r) = app.recorder {
+        if let Err(error) = r.flush() {
+            eprintln!("could not finish recording: {}", error);
         }
     }
 }
@@ -0,0 +1,82 @@
+use std::time::Duration;
+use crate::input::InputFrame;
+
+// how far the stick has to be pushed to count
+const STICK_THRESHOLD: f64 = 0.5;
+// holding the stick keeps stepping, after a pause
+const REPEAT_DELAY: Duration = Duration::from_millis(400);
+const REPEAT_INTERVAL: Duration = Duration::from_millis(120);
+
+/// Turns the movement stick into discrete steps, for moving through menus: one step when it
+/// is pushed, then more while it is held.
+#[derive(Default)]
+pub struct StickRepeat {
+    held: (i32, i32),
+    next_repeat: Duration
+}
+
+impl StickRepeat {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The steps to take this tick along x and y, each -1, 0 or 1.
+    pub fn update(&mut self, input: &InputFrame, now: Duration) -> (i32, i32) {
+        let direction = |value: f64| {
+            if value > STICK_THRESHOLD { 1 } else if value < -STICK_THRESHOLD { -1 } else { 0 }
+        };
+        let held = (direction(input.move_x), direction(input.move_y));
+        let repeat = held == self.held && now >= self.next_repeat;
+        if held != self.held {
+            self.next_repeat = now + REPEAT_DELAY;
+        } else if repeat {
+            self.next_repeat = now + REPEAT_INTERVAL;
+        }
+
+        let step = |held: i32, previous: i32| if held != previous || repeat { held } else { 0 };
+        let steps = (step(held.0, self.held.0), step(held.1, self.held.1));
+        self.held = held;
+        steps
+    }
+}
+
+/// A list of items picked with the stick or the arrow keys and chosen with confirm.
+pub struct Menu {
+    items: Vec<String>,
+    selected: usize,
+    stick: StickRepeat
+}
+
+impl Menu {
+    pub fn new(items: &[&str]) -> Self {
+        Self {
+            items: items.iter().map(|i| i.to_string()).collect(),
+            selected: 0,
+            stick: StickRepeat::new()
+        }
+    }
+
+    pub fn get_items(&self) -> &[String] {
+        &self.items
+    }
+
+    /// Relabels an item, e.g. one showing the value it changes.
+    pub fn set_item(&mut self, index: usize, item: &str) {
+        if let Some(i) = self.items.get_mut(index) {
+            *i = item.to_string();
+        }
+    }
+
+    pub fn get_selected(&self) -> usize {
+        self.selected
+    }
+
+    /// Moves the selection and returns the item chosen this tick, if any.
+    pub fn update(&mut self, input: &InputFrame, now: Duration) -> Option<usize> {
+        let (_, step) = self.stick.update(input, now);
+        if step != 0 && !self.items.is_empty() {
+            self.selected = (self.selected as i32 + step).rem_euclid(self.items.len() as i32) as usize;
+        }
+        input.confirm.then_some(self.selected)
+    }
+}
@@ -0,0 +1,86 @@
+use std::path::PathBuf;
+use clap::{ArgGroup, Parser};
+use planet_wrangler::settings::{DisplayMode, DisplaySettings};
+
+#[derive(Parser)]
+#[command(name = "planet-wrangler", about = "Tow planets into black holes while dodging enemies")]
+#[command(group(ArgGroup::new("mode").args(["windowed", "fullscreen", "borderless"])))]
+pub struct Options {
+    /// Seed for level layouts and enemy spawns. A random seed is used when omitted.
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Record every input to this file so the run can be replayed.
+    #[arg(long, value_name = "FILE")]
+    pub record: Option<PathBuf>,
+
+    /// Replay a recording made with --record instead of reading the controller.
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["record", "seed", "bot"])]
+    pub replay: Option<PathBuf>,
+
+    /// Let the computer play.
+    #[arg(long)]
+    pub bot: bool,
+
+    /// Read and save settings, such as controller bindings, in this file instead of the
+    /// default one in the config directory.
+    #[arg(long, value_name = "FILE")]
+    pub settings: Option<PathBuf>,
+
+    /// Read gameplay balance from this file instead of the default one in the config directory.
+    #[arg(long, value_name = "FILE")]
+    pub tuning: Option<PathBuf>,
+
+    /// Play the campaign in this file instead of the built-in levels.
+    #[arg(long, value_name = "FILE")]
+    pub levels: Option<PathBuf>,
+
+    /// Developer mode: F5 reloads the tuning file.
+    #[arg(long)]
+    pub dev: bool,
+
+    /// Play in a window.
+    #[arg(long)]
+    pub windowed: bool,
+
+    /// Play fullscreen.
+    #[arg(long)]
+    pub fullscreen: bool,
+
+    /// Play in a borderless window covering the desktop.
+    #[arg(long)]
+    pub borderless: bool,
+
+    /// Window width in windowed mode.
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub width: Option<u32>,
+
+    /// Window height in windowed mode.
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub height: Option<u32>,
+
+    /// Wait for the display's vertical sync before showing each frame.
+    #[arg(long, value_name = "BOOL")]
+    pub vsync: Option<bool>,
+
+    /// Frames drawn per second, at most.
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub fps: Option<u64>,
+}
+
+impl Options {
+    /// Overrides the display settings with any given on the command line.
+    pub fn apply_display(&self, display: &mut DisplaySettings) {
+        if self.windowed {
+            display.mode = DisplayMode::Windowed;
+        } else if self.fullscreen {
+            display.mode = DisplayMode::Fullscreen;
+        } else if self.borderless {
+            display.mode = DisplayMode::Borderless;
+        }
+        display.width = self.width.unwrap_or(display.width);
+        display.height = self.height.unwrap_or(display.height);
+        display.vsync = self.vsync.unwrap_or(display.vsync);
+        display.fps = self.fps.unwrap_or(display.fps);
+    }
+}
@@ -1,23 +1,26 @@
-use graphics::{Context, ImageSize};
-use opengl_graphics::{GlGraphics, Texture};
-use piston_window::TextureSettings;
-use rust_embed::EmbeddedFile;
-use sprite::Sprite;
-use std::collections::HashMap;
-use std::rc::Rc;
-use rand::Rng;
-use sdl2::rect::Rect;
+use std::collections::btree_map::Values;
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use crate::game::Game;
 use crate::game_sprite::GameSprite;
+use crate::rect::Rect;
+use crate::world::SpriteSize;
+use crate::save::HasSprite;
 
-#[derive(Clone, Copy, PartialEq)]
+pub const PLANET_FRAMES: u32 = 3;
+
+// how far a shot knocks a towed planet, enough to clear the ship
+const KNOCK_DISTANCE: f64 = 96.0;
+
+#[derive(Clone, Copy, Deserialize, PartialEq, Serialize)]
 pub enum PlanetState {
     InPlace,
     NotTowed,
     Towed
 }
 
+#[derive(Clone, Deserialize, Serialize)]
 pub struct Planet {
     sprite: GameSprite,
     sprite_index: u32,
@@ -37,6 +40,10 @@ impl Planet {
         self.sprite
     }
 
+    pub fn get_sprite_index(&self) -> u32 {
+        self.sprite_index
+    }
+
     pub fn get_state(&self) -> PlanetState {
         self.state
     }
@@ -52,6 +59,15 @@ impl Planet {
         self.state = PlanetState::NotTowed;
     }
 
+    /// Shot out of tow: pushed clear of the ship along `direction`, a unit vector, staying on
+    /// the playfield.
+    pub fn knocked_loose(&mut self, direction: (f64, f64), screen_width: f64, screen_height: f64) {
+        self.sprite.x += direction.0 * KNOCK_DISTANCE;
+        self.sprite.y += direction.1 * KNOCK_DISTANCE;
+        self.sprite.keep_inside(screen_width, screen_height);
+        self.state = PlanetState::NotTowed;
+    }
+
     pub fn towed(&mut self) {
         self.state = PlanetState::Towed;
     }
@@ -64,94 +80,53 @@ impl Planet {
     }
 }
 
+impl HasSprite for Planet {
+    fn get_sprite(&self) -> GameSprite {
+        self.sprite
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
 pub struct Planets {
-    done_sprite: Sprite<Texture>,
-    frames: u32,
-    planet_sprite: Sprite<Texture>,
-    planets: HashMap<Uuid, Planet>,
+    #[serde(with = "crate::save::sprite_map")]
+    planets: BTreeMap<Uuid, Planet>,
     sprite_height: u32,
-    sprite_width: u32,
-    window_height: f64,
-    window_width: f64
+    sprite_width: u32
 }
 
 impl Planets {
-    pub fn new(
-        done_file: &EmbeddedFile,
-        planet_file: &EmbeddedFile,
-        frames: u32, window_width: f64, window_height: f64
-    ) -> Self {
-        let done_image = image::load_from_memory(done_file.data.as_ref()).unwrap();
-        let done_texture = Texture::from_image(done_image.as_rgba8().unwrap(), &TextureSettings::new());
-        let planet_image = image::load_from_memory(planet_file.data.as_ref()).unwrap();
-        let planet_texture = Texture::from_image(planet_image.as_rgba8().unwrap(), &TextureSettings::new());
-        let sprite_width = planet_texture.get_width() / frames;
-        let sprite_height = planet_texture.get_height();
+    pub fn new(sprite_width: u32, sprite_height: u32) -> Self {
         Self {
-            done_sprite: Sprite::from_texture(Rc::new(done_texture)),
-            frames,
-            planet_sprite: Sprite::from_texture(Rc::new(planet_texture)),
-            planets: HashMap::new(),
+            planets: BTreeMap::new(),
             sprite_height,
-            sprite_width,
-            window_height,
-            window_width
+            sprite_width
         }
     }
 
-    pub fn get_planets(&mut self) -> &mut HashMap<Uuid, Planet> {
-        &mut self.planets
+    pub fn get_sprite_size(&self) -> SpriteSize {
+        SpriteSize::new(self.sprite_width, self.sprite_height)
     }
 
-    fn new_rect(&mut self) -> Rect {
-        let mut rng = rand::thread_rng();
-        let x = rng.gen_range(0, self.window_width as u32);
-        let y = rng.gen_range(0, self.window_height as u32);
+    pub fn get_planets(&mut self) -> &mut BTreeMap<Uuid, Planet> {
+        &mut self.planets
+    }
 
-        Rect::new(x as i32, y as i32, x + self.sprite_width, y + self.sprite_height)
+    pub fn iter(&self) -> Values<'_, Uuid, Planet> {
+        self.planets.values()
     }
 
-    pub fn draw(&mut self, ctx: Context, gl: &mut GlGraphics) {
-        for planet in self.planets.values_mut() {
-            self.planet_sprite.set_src_rect([
-                self.sprite_width as f64 * planet.sprite_index as f64,
-                0.0,
-                self.sprite_width as f64,
-                self.sprite_height as f64
-            ]);
-            planet.sprite.draw(&mut self.planet_sprite, ctx, gl);
-            if planet.get_state() == PlanetState::InPlace {
-                self.done_sprite.set_position(planet.sprite.x, planet.sprite.y);
-                self.done_sprite.draw(ctx.transform, gl);
-            }
+    /// Puts a new level's planets in place, each with its sprite.
+    pub fn place(&mut self, planets: &[(Rect, u32)]) {
+        self.planets.clear();
+        for &(r, sprite_index) in planets {
+            let p = Planet::new(r.x as f64, r.y as f64, sprite_index, self.sprite_width, self.sprite_height);
+            self.planets.insert(p.sprite.get_id(), p);
         }
     }
 
     pub fn update(&mut self, game: &Game) {
-        if self.planets.is_empty() {
-            for i in 0..game.black_hole_count {
-                let mut got_rect = false;
-                let mut r = self.new_rect();
-                while !got_rect {
-                    got_rect = true;
-                    for h in game.black_holes.iter() {
-                        if r.has_intersection(*h) {
-                            got_rect = false;
-                            r = self.new_rect();
-                            break;
-                        }
-                    }
-                }
-                let p = Planet::new(
-                    r.x as f64, r.y as f64, i.rem_euclid(self.frames),
-                    self.sprite_width, self.sprite_height
-                );
-                self.planets.insert(p.sprite.get_id(), p);
-            }
-        } else {
-            for (_, planet) in self.planets.iter_mut() {
-                planet.update(game.player);
-            }
+        for planet in self.planets.values_mut() {
+            planet.update(game.player);
         }
     }
 
@@ -1,24 +1,9 @@
-use graphics::Context;
-use opengl_graphics::{
-    GlGraphics,
-    ImageSize,
-    Texture
-};
-use piston::{
-    Size,
-};
-use piston_window::{
-    TextureSettings
-};
-use sprite::Sprite;
-use std::rc::Rc;
-use rust_embed::EmbeddedFile;
+use serde::{Deserialize, Serialize};
 use crate::game::Game;
 use crate::game_sprite::GameSprite;
+use crate::power_up::PowerUpKind;
 
-const PLAYER_DIE_INCREMENT: f64 = 1.0;
-
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, Deserialize, PartialEq, Serialize)]
 pub enum PlayerState {
     NotTowing,
     Towing,
@@ -26,9 +11,9 @@ pub enum PlayerState {
     Dead
 }
 
+#[derive(Clone, Deserialize, Serialize)]
 pub struct Player {
     sprite: GameSprite,
-    sprite_texture: Sprite<Texture>,
     start_x: f64,
     start_y: f64,
     start_height: f64,
@@ -39,27 +24,22 @@ pub struct Player {
 }
 
 impl Player {
-    pub fn new(window_width: f64, window_height: f64, sprite_file: &EmbeddedFile) -> Self {
-        let image = image::load_from_memory(sprite_file.data.as_ref()).unwrap();
-        let texture = Texture::from_image(image.as_rgba8().unwrap(), &TextureSettings::new());
-        let size = texture.get_size();
-        let half_size = Size::from([size.0 / 2, size.1 / 2]);
-        let start_x = (window_width / 2.0) - half_size.width;
-        let start_y = (window_height / 2.0) - half_size.height;
+    pub fn new(window_width: f64, window_height: f64, sprite_width: u32, sprite_height: u32) -> Self {
+        let start_x = (window_width / 2.0) - (sprite_width / 2) as f64;
+        let start_y = (window_height / 2.0) - (sprite_height / 2) as f64;
         Self {
-            sprite: GameSprite::new((window_width / 2.0) - half_size.width, (window_height / 2.0) - half_size.height, size.0 as f64, size.1 as f64),
-            sprite_texture: Sprite::from_texture(Rc::new(texture)),
+            sprite: GameSprite::new(start_x, start_y, sprite_width as f64, sprite_height as f64),
             start_x,
             start_y,
-            start_height: size.1 as f64,
-            start_width: size.0 as f64,
+            start_height: sprite_height as f64,
+            start_width: sprite_width as f64,
             state: PlayerState::NotTowing,
             window_width,
             window_height
         }
     }
 
-    pub fn get_state(&mut self) -> PlayerState {
+    pub fn get_state(&self) -> PlayerState {
         self.state
     }
 
@@ -67,6 +47,11 @@ impl Player {
         self.sprite
     }
 
+    /// Whether a shield power-up is keeping enemies and their shots off the ship.
+    pub fn is_shielded(&self, game: &Game) -> bool {
+        self.state != PlayerState::Dying && game.effects.is_active(PowerUpKind::Shield)
+    }
+
     pub fn not_towing(&mut self) {
         self.state = PlayerState::NotTowing;
     }
@@ -80,34 +65,27 @@ impl Player {
         self.sprite.shatter_start();
     }
 
-    pub fn draw(&mut self, ctx: Context, gl: &mut GlGraphics) {
+    pub fn update(&mut self, game: &Game, dt: f64) {
         match self.state {
             PlayerState::Dying => {
-                self.sprite.shatter(&mut self.sprite_texture, self.start_width as u32, self.start_height as u32, ctx, gl);
-            }
-            PlayerState::Dead => {}
-            _ => {
-                self.sprite_texture.set_src_rect([0.0, 0.0, self.sprite.width, self.sprite.height]);
-                self.sprite.draw(&mut self.sprite_texture, ctx, gl);
-            }
-        }
-    }
-
-    pub fn update(&mut self, game: &Game) {
-        match self.state {
-            PlayerState::Dying => {
-                if self.sprite.shatter_update(PLAYER_DIE_INCREMENT, self.window_width, self.window_height) {
+                if self.sprite.shatter_update(game.tuning.player_die_speed * dt, self.window_width, self.window_height) {
                     self.state = PlayerState::Dead;
                 }
             }
             PlayerState::Dead => {}
             _ => {
-                let left_stick_pos = game.controller.get_left_stick();
-                self.sprite.degrees = left_stick_pos.get_degrees() + 90.0;
+                let input = game.input;
+                let boost = if game.effects.is_active(PowerUpKind::Speed) { game.tuning.speed_boost } else { 1.0 };
+                let speed = game.tuning.player_speed * boost;
+                // keep facing the same way once the stick is let go
+                if input.move_x != 0.0 || input.move_y != 0.0 {
+                    self.sprite.degrees = input.get_move_degrees() + 90.0;
+                }
                 self.sprite.set_position(
-                    (self.sprite.x + (left_stick_pos.get_x() * 1.0)).min(self.window_width).max(0.0),
-                    (self.sprite.y + (left_stick_pos.get_y() * 1.0)).min(self.window_height).max(0.0)
+                    self.sprite.x + (input.move_x * speed * dt),
+                    self.sprite.y + (input.move_y * speed * dt)
                 );
+                self.sprite.keep_inside(self.window_width, self.window_height);
             }
         }
     }
@@ -0,0 +1,199 @@
+use std::collections::btree_map::Values;
+use std::collections::BTreeMap;
+use std::time::Duration;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use crate::clock::Clock;
+use crate::game::Game;
+use crate::game_sprite::GameSprite;
+use crate::rect::Rect;
+use crate::rng::GameRng;
+use crate::save::HasSprite;
+use crate::tuning::Tuning;
+
+pub const POWER_UP_KINDS: usize = 6;
+
+// how long the HUD shows a power-up that takes effect all at once
+const NOTICE_DURATION: Duration = Duration::from_millis(2000);
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum PowerUpKind {
+    Shield,
+    Spread,
+    RapidFire,
+    Speed,
+    SmartBomb,
+    ExtraLife
+}
+
+impl PowerUpKind {
+    pub const ALL: [PowerUpKind; POWER_UP_KINDS] = [
+        PowerUpKind::Shield, PowerUpKind::Spread, PowerUpKind::RapidFire,
+        PowerUpKind::Speed, PowerUpKind::SmartBomb, PowerUpKind::ExtraLife
+    ];
+
+    pub fn random(rng: &mut GameRng) -> Self {
+        Self::ALL[rng.gen_range(0, POWER_UP_KINDS)]
+    }
+
+    /// Its frame in the power-up sprite sheet.
+    pub fn get_frame(self) -> u32 {
+        self as u32
+    }
+
+    /// What the HUD calls it.
+    pub fn get_label(self) -> &'static str {
+        match self {
+            PowerUpKind::Shield => "Shield",
+            PowerUpKind::Spread => "Spread",
+            PowerUpKind::RapidFire => "Rapid fire",
+            PowerUpKind::Speed => "Speed",
+            PowerUpKind::SmartBomb => "Smart bomb",
+            PowerUpKind::ExtraLife => "Extra life"
+        }
+    }
+
+    /// Whether it does all it does the moment it's picked up, rather than lasting a while.
+    pub fn is_instant(self) -> bool {
+        matches!(self, PowerUpKind::SmartBomb | PowerUpKind::ExtraLife)
+    }
+
+    /// How long it lasts, or how long the HUD shows it for one that's instant.
+    pub fn get_duration(self, tuning: &Tuning) -> Duration {
+        if self.is_instant() { NOTICE_DURATION } else { tuning.get_power_up_duration() }
+    }
+}
+
+/// The power-ups in effect, each with the time it has left. Kept with the `Game`, so the
+/// ship and its lasers can see what they're powered up with.
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct Effects {
+    time_left: [Duration; POWER_UP_KINDS]
+}
+
+impl Effects {
+    /// Starts a power-up, or starts it over when it's already going.
+    pub fn start(&mut self, kind: PowerUpKind, duration: Duration) {
+        self.time_left[kind as usize] = duration;
+    }
+
+    pub fn is_active(&self, kind: PowerUpKind) -> bool {
+        !self.time_left[kind as usize].is_zero()
+    }
+
+    /// Every power-up in effect, with the time it has left.
+    pub fn iter(&self) -> impl Iterator<Item = (PowerUpKind, Duration)> + '_ {
+        PowerUpKind::ALL.into_iter()
+            .map(|kind| (kind, self.time_left[kind as usize]))
+            .filter(|(_, time_left)| !time_left.is_zero())
+    }
+
+    /// Runs the timers down by `elapsed`.
+    pub fn update(&mut self, elapsed: Duration) {
+        for time_left in self.time_left.iter_mut() {
+            *time_left = time_left.saturating_sub(elapsed);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.time_left = [Duration::ZERO; POWER_UP_KINDS];
+    }
+}
+
+/// A power-up waiting on the playfield to be picked up.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct PowerUp {
+    dropped: Duration,
+    kind: PowerUpKind,
+    sprite: GameSprite
+}
+
+impl PowerUp {
+    pub fn get_kind(&self) -> PowerUpKind {
+        self.kind
+    }
+
+    pub fn get_sprite(&self) -> GameSprite {
+        self.sprite
+    }
+}
+
+impl HasSprite for PowerUp {
+    fn get_sprite(&self) -> GameSprite {
+        self.sprite
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct PowerUps {
+    /// When one last turned up on its own, or nothing until the level gets going.
+    last_spawn: Option<Duration>,
+    #[serde(with = "crate::save::sprite_map")]
+    power_ups: BTreeMap<Uuid, PowerUp>,
+    sprite_height: u32,
+    sprite_width: u32
+}
+
+impl PowerUps {
+    pub fn new(sprite_width: u32, sprite_height: u32) -> Self {
+        Self {
+            last_spawn: None,
+            power_ups: BTreeMap::new(),
+            sprite_height,
+            sprite_width
+        }
+    }
+
+    pub fn iter(&self) -> Values<'_, Uuid, PowerUp> {
+        self.power_ups.values()
+    }
+
+    /// Leaves a power-up with its middle at `(x, y)`, such as where an enemy was destroyed.
+    pub fn add(&mut self, kind: PowerUpKind, (x, y): (f64, f64), now: Duration) {
+        let sprite = GameSprite::new(
+            x - self.sprite_width as f64 / 2.0, y - self.sprite_height as f64 / 2.0,
+            self.sprite_width as f64, self.sprite_height as f64
+        );
+        let power_up = PowerUp { dropped: now, kind, sprite };
+        self.power_ups.insert(power_up.sprite.get_id(), power_up);
+    }
+
+    /// Picks up every power-up the ship is touching. Gives what was picked up.
+    pub fn collect(&mut self, ship: Rect) -> Vec<PowerUpKind> {
+        let mut collected = vec![];
+        self.power_ups.retain(|_, p| {
+            let touching = p.sprite.get_position().has_intersection(ship);
+            if touching {
+                collected.push(p.kind);
+            }
+            !touching
+        });
+        collected
+    }
+
+    /// Clears away the power-ups left too long, and now and then puts one somewhere at random.
+    pub fn update(&mut self, game: &Game, clock: &dyn Clock, rng: &mut GameRng) {
+        let tuning = &game.tuning;
+        let lifetime = tuning.get_power_up_lifetime();
+        self.power_ups.retain(|_, p| clock.since(p.dropped) < lifetime);
+
+        let last_spawn = *self.last_spawn.get_or_insert(clock.now());
+        if clock.since(last_spawn) < tuning.get_power_up_spawn_interval() {
+            return;
+        }
+        self.last_spawn = Some(clock.now());
+        if self.power_ups.len() >= tuning.max_power_ups {
+            return;
+        }
+        let (width, height) = (self.sprite_width as f64, self.sprite_height as f64);
+        let x = rng.gen_range(width, (game.screen_width - width).max(width + 1.0));
+        let y = rng.gen_range(height, (game.screen_height - height).max(height + 1.0));
+        self.add(PowerUpKind::random(rng), (x, y), clock.now());
+    }
+
+    pub fn reset(&mut self) {
+        self.power_ups.clear();
+        self.last_spawn = None;
+    }
+}
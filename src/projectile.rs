@@ -0,0 +1,92 @@
+use std::collections::btree_map::Values;
+use std::collections::BTreeMap;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use crate::clock::Clock;
+use crate::game::Game;
+use crate::game_sprite::GameSprite;
+use crate::save::HasSprite;
+use crate::steering::towards;
+
+/// A shot fired by an enemy. It flies straight until it hits something, leaves the playfield
+/// or runs out of time.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Projectile {
+    fired: Duration,
+    sprite: GameSprite,
+    velocity: (f64, f64)
+}
+
+impl Projectile {
+    pub fn get_sprite(&self) -> GameSprite {
+        self.sprite
+    }
+
+    /// Which way it's flying, one unit long.
+    pub fn get_direction(&self) -> (f64, f64) {
+        towards((0.0, 0.0), self.velocity)
+    }
+
+    fn is_off_screen(&self, window_width: f64, window_height: f64) -> bool {
+        self.sprite.x + self.sprite.width < 0.0 || self.sprite.x > window_width ||
+            self.sprite.y + self.sprite.height < 0.0 || self.sprite.y > window_height
+    }
+}
+
+impl HasSprite for Projectile {
+    fn get_sprite(&self) -> GameSprite {
+        self.sprite
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Projectiles {
+    #[serde(with = "crate::save::sprite_map")]
+    projectiles: BTreeMap<Uuid, Projectile>,
+    sprite_height: u32,
+    sprite_width: u32
+}
+
+impl Projectiles {
+    pub fn new(sprite_width: u32, sprite_height: u32) -> Self {
+        Self {
+            projectiles: BTreeMap::new(),
+            sprite_height,
+            sprite_width
+        }
+    }
+
+    /// Fires a shot from `from` towards `to`, both the middles of things on the playfield.
+    pub fn fire(&mut self, from: (f64, f64), to: (f64, f64), speed: f64, now: Duration) {
+        let (x, y) = towards(from, to);
+        let mut sprite = GameSprite::new(
+            from.0 - self.sprite_width as f64 / 2.0, from.1 - self.sprite_height as f64 / 2.0,
+            self.sprite_width as f64, self.sprite_height as f64
+        );
+        sprite.degrees = y.atan2(x).to_degrees() + 90.0;
+        let projectile = Projectile { fired: now, sprite, velocity: (x * speed, y * speed) };
+        self.projectiles.insert(projectile.sprite.get_id(), projectile);
+    }
+
+    pub fn remove(&mut self, id: &Uuid) {
+        self.projectiles.remove(id);
+    }
+
+    pub fn iter(&self) -> Values<'_, Uuid, Projectile> {
+        self.projectiles.values()
+    }
+
+    pub fn update(&mut self, game: &Game, clock: &dyn Clock, dt: f64) {
+        let lifetime = game.tuning.get_projectile_lifetime();
+        self.projectiles.retain(|_, p| {
+            p.sprite.x += p.velocity.0 * dt;
+            p.sprite.y += p.velocity.1 * dt;
+            clock.since(p.fired) < lifetime && !p.is_off_screen(game.screen_width, game.screen_height)
+        });
+    }
+
+    pub fn reset(&mut self) {
+        self.projectiles.clear();
+    }
+}
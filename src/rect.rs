@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub w: u32,
+    pub h: u32
+}
+
+impl Rect {
+    pub fn new(x: i32, y: i32, w: u32, h: u32) -> Self {
+        Self { x, y, w, h }
+    }
+
+    pub fn x(&self) -> i32 {
+        self.x
+    }
+
+    pub fn y(&self) -> i32 {
+        self.y
+    }
+
+    /// This rect grown by `margin` on every side.
+    pub fn inflate(&self, margin: u32) -> Rect {
+        Rect::new(self.x - margin as i32, self.y - margin as i32, self.w + margin * 2, self.h + margin * 2)
+    }
+
+    pub fn has_intersection(&self, other: Rect) -> bool {
+        // empty rects never intersect, matching SDL_HasIntersection
+        if self.w == 0 || self.h == 0 || other.w == 0 || other.h == 0 {
+            return false;
+        }
+
+        (self.x < other.x + other.w as i32) && (other.x < self.x + self.w as i32) &&
+            (self.y < other.y + other.h as i32) && (other.y < self.y + self.h as i32)
+    }
+}
@@ -0,0 +1,426 @@
+use std::rc::Rc;
+use graphics::character::CharacterCache;
+use graphics::{clear, ellipse, line_from_to, rectangle, text, Context, Ellipse, Transformed};
+use opengl_graphics::{GlGraphics, GlyphCache, ImageSize, Texture};
+use piston_window::{color, TextureSettings};
+use planet_wrangler::black_hole::{BlackHoles, BlackHoleState};
+use planet_wrangler::boss::{Arena, BossState, BOSS_FRAMES};
+use planet_wrangler::enemy::{Enemies, EnemyState, ENEMY_FRAMES, ENEMY_SHEETS};
+use planet_wrangler::game::{Game, GameState};
+use planet_wrangler::game_sprite::GameSprite;
+use planet_wrangler::laser::Lasers;
+use planet_wrangler::menu::Menu;
+use planet_wrangler::planets::{Planets, PlanetState, PLANET_FRAMES};
+use planet_wrangler::player::{Player, PlayerState};
+use planet_wrangler::power_up::{PowerUps, POWER_UP_KINDS};
+use planet_wrangler::projectile::Projectiles;
+use planet_wrangler::viewport::{Viewport, VIEW_HEIGHT, VIEW_WIDTH};
+use planet_wrangler::world::{SpriteSize, SpriteSizes, World};
+use rust_embed::EmbeddedFile;
+use sprite::Sprite;
+
+// one sprite sheet per spawning kind, in `EnemyKind::get_sheet` order
+const ENEMY_FILES: [&str; ENEMY_SHEETS] = ["chaser.png", "wanderer.png", "orbiter.png", "splitter.png", "gunner.png"];
+
+fn load_sprite(sprite_file: &EmbeddedFile) -> Sprite<Texture> {
+    let image = image::load_from_memory(sprite_file.data.as_ref()).unwrap();
+    let texture = Texture::from_image(image.as_rgba8().unwrap(), &TextureSettings::new());
+    Sprite::from_texture(Rc::new(texture))
+}
+
+fn frame_size(sprite: &Sprite<Texture>, frames: u32) -> SpriteSize {
+    let size = sprite.get_texture().get_size();
+    SpriteSize::new(size.0 / frames, size.1)
+}
+
+fn draw_sprite(sprite: &mut Sprite<Texture>, game_sprite: &GameSprite, ctx: Context, gl: &mut GlGraphics) {
+    sprite.set_position(game_sprite.x, game_sprite.y);
+    sprite.set_rotation(game_sprite.degrees);
+    sprite.draw(ctx.transform, gl);
+}
+
+fn draw_shatter(sprite: &mut Sprite<Texture>, game_sprite: &GameSprite, size: SpriteSize, ctx: Context, gl: &mut GlGraphics) {
+    let [shatter_x, shatter_y, shatter_width, shatter_height] = game_sprite.get_shatter_rect();
+    let width = size.width as f64 / 2.0;
+    let height = size.height as f64 / 2.0;
+    // where the pieces go depends on how big they're drawn
+    let (scale_x, scale_y) = sprite.get_scale();
+    sprite.set_src_rect([0.0, 0.0, width, height]);
+    sprite.set_position(shatter_x, shatter_y);
+    sprite.draw(ctx.transform, gl);
+
+    sprite.set_src_rect([width, 0.0, width, height]);
+    sprite.set_position(shatter_x + shatter_width - width * scale_x, shatter_y);
+    sprite.draw(ctx.transform, gl);
+
+    sprite.set_src_rect([0.0, height, width, height]);
+    sprite.set_position(shatter_x + shatter_width - width * scale_x, shatter_y + shatter_height - height * scale_y);
+    sprite.draw(ctx.transform, gl);
+
+    sprite.set_src_rect([width, height, width, height]);
+    sprite.set_position(shatter_x, shatter_y + shatter_height - height * scale_y);
+    sprite.draw(ctx.transform, gl);
+}
+
+// drawn over the playfield while the game is paused
+const PAUSE_DIM: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+// the boss's health bar, across the top of the playfield
+const HEALTH_BACK: [f32; 4] = [0.3, 0.0, 0.0, 1.0];
+const HEALTH_FILL: [f32; 4] = [0.9, 0.1, 0.1, 1.0];
+const HEALTH_HEIGHT: f64 = 12.0;
+// sealed black holes are drawn faded until the boss is beaten
+const SEALED_OPACITY: f32 = 0.35;
+// the line a gunner draws while taking aim
+const AIM_LINE: [f32; 4] = [1.0, 0.3, 0.1, 0.5];
+// the ring around a shielded ship
+const SHIELD_RING: [f32; 4] = [0.4, 0.7, 1.0, 0.8];
+const TITLE_SIZE: u32 = 24;
+const TEXT_SIZE: u32 = 14;
+const LINE_HEIGHT: f64 = 28.0;
+
+/// The boss's health bar, centered across the top of the playfield while it's alive.
+fn draw_boss_health(arena: &Arena, width: f64, ctx: Context, gl: &mut GlGraphics) {
+    let Some(boss) = arena.get_boss().filter(|b| b.get_state() == BossState::Alive) else {
+        return;
+    };
+    let bar_width = width * 0.4;
+    let x = (width - bar_width) / 2.0;
+    rectangle(HEALTH_BACK, [x, HEALTH_HEIGHT, bar_width, HEALTH_HEIGHT], ctx.transform, gl);
+    rectangle(HEALTH_FILL, [x, HEALTH_HEIGHT, bar_width * boss.get_health(), HEALTH_HEIGHT], ctx.transform, gl);
+}
+
+/// Draws a line of text centered across the view, with its baseline at `y`.
+fn draw_centered(
+    line: &str, size: u32, y: f64, view_width: f64,
+    glyphs: &mut GlyphCache, ctx: Context, gl: &mut GlGraphics
+) {
+    let width = glyphs.width(size, line).unwrap_or(0.0);
+    let transform = ctx.transform.trans((view_width - width) / 2.0, y);
+    text::Text::new_color(color::YELLOW, size).draw(
+        line, glyphs, &ctx.draw_state, transform, gl
+    ).unwrap();
+}
+
+/// A menu's items, one per line from `top`, with the selected one marked. Every item is padded
+/// to the same width so the column doesn't shift as the selection moves.
+fn draw_menu(menu: &Menu, top: f64, view_width: f64, glyphs: &mut GlyphCache, ctx: Context, gl: &mut GlGraphics) {
+    let longest = menu.get_items().iter().map(|item| item.chars().count()).max().unwrap_or(0);
+    for (i, item) in menu.get_items().iter().enumerate() {
+        let marker = if i == menu.get_selected() { ">" } else { " " };
+        let line = format!("{} {:<width$}", marker, item, width = longest);
+        draw_centered(&line, TEXT_SIZE, top + LINE_HEIGHT * i as f64, view_width, glyphs, ctx, gl);
+    }
+}
+
+/// Moves drawing into the logical view, scaled to fit the window.
+fn view_context(ctx: Context, viewport: &Viewport) -> Context {
+    let (offset_x, offset_y) = viewport.get_offset();
+    let scale = viewport.get_scale();
+    ctx.trans(offset_x, offset_y).scale(scale, scale)
+}
+
+/// Covers the bars around the logical view, so nothing drawn past its edges shows.
+fn draw_letterbox(viewport: &Viewport, ctx: Context, gl: &mut GlGraphics) {
+    let (window_width, window_height) = viewport.get_window_size();
+    let (offset_x, offset_y) = viewport.get_offset();
+    let bars = [
+        [0.0, 0.0, offset_x, window_height],
+        [window_width - offset_x, 0.0, offset_x, window_height],
+        [0.0, 0.0, window_width, offset_y],
+        [0.0, window_height - offset_y, window_width, offset_y]
+    ];
+    for bar in bars {
+        if bar[2] > 0.0 && bar[3] > 0.0 {
+            rectangle(color::BLACK, bar, ctx.transform, gl);
+        }
+    }
+}
+
+/// What a menu screen shows: a title, some lines and then a menu, if it has one.
+pub struct Screen<'a> {
+    pub title: &'a str,
+    pub lines: &'a [String],
+    pub menu: Option<&'a Menu>
+}
+
+/// Owns every texture and draws snapshots of a `World`.
+pub struct Renderer {
+    black_hole_sprite: Sprite<Texture>,
+    boss_sprite: Sprite<Texture>,
+    done_sprite: Sprite<Texture>,
+    enemy_sprites: Vec<Sprite<Texture>>,
+    laser_sprite: Sprite<Texture>,
+    planet_sprite: Sprite<Texture>,
+    player_sprite: Sprite<Texture>,
+    power_up_sprite: Sprite<Texture>,
+    projectile_sprite: Sprite<Texture>,
+    sizes: SpriteSizes
+}
+
+impl Renderer {
+    /// Loads every texture, fetching each image by its file name.
+    pub fn new(asset: impl Fn(&str) -> EmbeddedFile) -> Self {
+        let load = |name: &str| load_sprite(&asset(name));
+        let black_hole_sprite = load("black-hole.png");
+        let boss_sprite = load("boss.png");
+        let enemy_sprites: Vec<Sprite<Texture>> = ENEMY_FILES.iter().map(|name| load(name)).collect();
+        let laser_sprite = load("laser.png");
+        let planet_sprite = load("planets.png");
+        let player_sprite = load("hero.png");
+        let power_up_sprite = load("power-ups.png");
+        let projectile_sprite = load("projectile.png");
+        let sizes = SpriteSizes {
+            black_hole: frame_size(&black_hole_sprite, 1),
+            boss: frame_size(&boss_sprite, BOSS_FRAMES),
+            enemies: std::array::from_fn(|i| frame_size(&enemy_sprites[i], ENEMY_FRAMES)),
+            laser: frame_size(&laser_sprite, 1),
+            planet: frame_size(&planet_sprite, PLANET_FRAMES),
+            player: frame_size(&player_sprite, 1),
+            power_up: frame_size(&power_up_sprite, POWER_UP_KINDS as u32),
+            projectile: frame_size(&projectile_sprite, 1)
+        };
+
+        Self {
+            black_hole_sprite,
+            boss_sprite,
+            done_sprite: load("done.png"),
+            enemy_sprites,
+            laser_sprite,
+            planet_sprite,
+            player_sprite,
+            power_up_sprite,
+            projectile_sprite,
+            sizes
+        }
+    }
+
+    pub fn get_sprite_sizes(&self) -> SpriteSizes {
+        self.sizes
+    }
+
+    /// The game being played: the HUD, the playfield and whatever message the state calls for.
+    pub fn draw_world(
+        &mut self, world: &World, viewport: &Viewport,
+        glyphs: &mut GlyphCache, window_ctx: Context, gl: &mut GlGraphics
+    ) {
+        let game = world.get_game();
+        let game_height = game.screen_height;
+
+        clear(color::BLACK, gl);
+        let ctx = view_context(window_ctx, viewport);
+        let view_width = VIEW_WIDTH;
+        let y = VIEW_HEIGHT - 30.0;
+        let mut transform = ctx.transform.trans(48.0, y);
+        text::Text::new_color(color::YELLOW, 24).draw(
+            &format!("{}", game.score), glyphs, &ctx.draw_state, transform, gl
+        ).unwrap();
+
+        transform = ctx.transform.trans((view_width * 0.25) - (24.0 * 2.0), y);
+        text::Text::new_color(color::YELLOW, 24).draw(
+            &format!("Level {}", game.get_level()), glyphs, &ctx.draw_state, transform, gl
+        ).unwrap();
+
+        transform = ctx.transform.trans((view_width / 2.0) - (24.0 * 4.0), y);
+        text::Text::new_color(color::YELLOW, 24).draw(
+            &format!("High: {}", game.high_score), glyphs, &ctx.draw_state, transform, gl
+        ).unwrap();
+
+        transform = ctx.transform.trans(view_width - 72.0, y);
+        text::Text::new_color(color::YELLOW, 24).draw(
+            &format!("{}", game.lives), glyphs, &ctx.draw_state, transform, gl
+        ).unwrap();
+
+        if let Some(time_left) = world.get_time_left() {
+            transform = ctx.transform.trans((view_width * 0.75) - (24.0 * 2.0), y);
+            text::Text::new_color(color::YELLOW, 24).draw(
+                &format!("{}", time_left.as_secs_f64().ceil()), glyphs, &ctx.draw_state, transform, gl
+            ).unwrap();
+        }
+
+        // the power-ups in effect down the left of the playfield, with the seconds the timed ones have left
+        for (i, (kind, time_left)) in game.effects.iter().enumerate() {
+            let label = if kind.is_instant() {
+                kind.get_label().to_string()
+            } else {
+                format!("{} {}", kind.get_label(), time_left.as_secs_f64().ceil())
+            };
+            transform = ctx.transform.trans(16.0, LINE_HEIGHT * (i + 1) as f64);
+            text::Text::new_color(color::YELLOW, TEXT_SIZE).draw(
+                &label, glyphs, &ctx.draw_state, transform, gl
+            ).unwrap();
+        }
+
+        match game.state {
+            GameState::Starting | GameState::Over => {}
+            GameState::Dead => {
+                draw_centered("Got you. Press any key to continue", TEXT_SIZE, game_height / 2.0, view_width, glyphs, ctx, gl);
+            }
+            GameState::LevelComplete => {
+                draw_centered("Level complete. Press any key to continue", TEXT_SIZE, game_height / 2.0, view_width, glyphs, ctx, gl);
+            }
+            GameState::Paused => {
+                self.draw_playfield(world, ctx, gl);
+                rectangle(PAUSE_DIM, [0.0, 0.0, view_width, game_height], ctx.transform, gl);
+                if let Some(menu) = world.get_pause_menu() {
+                    draw_menu(menu, game_height / 2.0, view_width, glyphs, ctx, gl);
+                }
+            }
+            _ => self.draw_playfield(world, ctx, gl)
+        }
+        draw_letterbox(viewport, window_ctx, gl);
+    }
+
+    fn draw_playfield(&mut self, world: &World, ctx: Context, gl: &mut GlGraphics) {
+        self.draw_black_holes(world.get_black_holes(), ctx, gl);
+        self.draw_planets(world.get_planets(), ctx, gl);
+        self.draw_power_ups(world.get_power_ups(), ctx, gl);
+        self.draw_player(world.get_player(), world.get_game(), ctx, gl);
+        self.draw_lasers(world.get_lasers(), ctx, gl);
+        self.draw_enemies(world.get_enemies(), ctx, gl);
+        self.draw_boss(world.get_arena(), ctx, gl);
+        self.draw_projectiles(world.get_projectiles(), ctx, gl);
+        draw_boss_health(world.get_arena(), world.get_game().screen_width, ctx, gl);
+    }
+
+    /// A screen of centered text, for everything but the game itself.
+    pub fn draw_screen(
+        &mut self, screen: Screen, viewport: &Viewport,
+        glyphs: &mut GlyphCache, window_ctx: Context, gl: &mut GlGraphics
+    ) {
+        clear(color::BLACK, gl);
+        let ctx = view_context(window_ctx, viewport);
+        let view_width = VIEW_WIDTH;
+        let mut y = VIEW_HEIGHT / 4.0;
+        draw_centered(screen.title, TITLE_SIZE, y, view_width, glyphs, ctx, gl);
+        y += LINE_HEIGHT * 2.0;
+        for line in screen.lines {
+            draw_centered(line, TEXT_SIZE, y, view_width, glyphs, ctx, gl);
+            y += LINE_HEIGHT;
+        }
+        if let Some(menu) = screen.menu {
+            draw_menu(menu, y + LINE_HEIGHT, view_width, glyphs, ctx, gl);
+        }
+        draw_letterbox(viewport, window_ctx, gl);
+    }
+
+    fn draw_black_holes(&mut self, black_holes: &BlackHoles, ctx: Context, gl: &mut GlGraphics) {
+        for black_hole in black_holes.iter() {
+            let opacity = if black_hole.get_state() == BlackHoleState::Sealed { SEALED_OPACITY } else { 1.0 };
+            self.black_hole_sprite.set_opacity(opacity);
+            draw_sprite(&mut self.black_hole_sprite, &black_hole.get_sprite(), ctx, gl);
+        }
+    }
+
+    fn draw_boss(&mut self, arena: &Arena, ctx: Context, gl: &mut GlGraphics) {
+        let Some(boss) = arena.get_boss() else {
+            return;
+        };
+        let size = self.sizes.boss;
+        match boss.get_state() {
+            BossState::Alive => {
+                self.boss_sprite.set_src_rect([
+                    size.width as f64 * boss.get_sprite_index() as f64,
+                    0.0,
+                    size.width as f64,
+                    size.height as f64
+                ]);
+                draw_sprite(&mut self.boss_sprite, &boss.get_sprite(), ctx, gl);
+            }
+            BossState::Dying => draw_shatter(&mut self.boss_sprite, &boss.get_sprite(), size, ctx, gl),
+            BossState::Dead => {}
+        }
+    }
+
+    fn draw_enemies(&mut self, enemies: &Enemies, ctx: Context, gl: &mut GlGraphics) {
+        for enemy in enemies.iter() {
+            let kind = enemy.get_kind();
+            let size = self.sizes.enemies[kind.get_sheet()];
+            let sprite = &mut self.enemy_sprites[kind.get_sheet()];
+            sprite.set_scale(kind.get_scale(), kind.get_scale());
+            match enemy.get_state() {
+                EnemyState::Alive => {
+                    sprite.set_src_rect([
+                        size.width as f64 * enemy.get_sprite_index() as f64,
+                        0.0,
+                        size.width as f64,
+                        size.height as f64
+                    ]);
+                    draw_sprite(sprite, &enemy.get_sprite(), ctx, gl);
+                    if let Some((x, y)) = enemy.get_aim() {
+                        // sprites are drawn centered on their position, so shift the line the
+                        // same way the shot will be
+                        let offset = self.sizes.projectile.width as f64 / 2.0;
+                        let (from_x, from_y) = enemy.get_center();
+                        line_from_to(AIM_LINE, 1.0, [from_x - offset, from_y - offset], [x - offset, y - offset], ctx.transform, gl);
+                    }
+                }
+                EnemyState::Dying => {
+                    draw_shatter(sprite, &enemy.get_sprite(), size, ctx, gl);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn draw_lasers(&mut self, lasers: &Lasers, ctx: Context, gl: &mut GlGraphics) {
+        for laser in lasers.iter() {
+            draw_sprite(&mut self.laser_sprite, &laser.get_sprite(), ctx, gl);
+        }
+    }
+
+    fn draw_projectiles(&mut self, projectiles: &Projectiles, ctx: Context, gl: &mut GlGraphics) {
+        for projectile in projectiles.iter() {
+            draw_sprite(&mut self.projectile_sprite, &projectile.get_sprite(), ctx, gl);
+        }
+    }
+
+    fn draw_planets(&mut self, planets: &Planets, ctx: Context, gl: &mut GlGraphics) {
+        let size = self.sizes.planet;
+        for planet in planets.iter() {
+            self.planet_sprite.set_src_rect([
+                size.width as f64 * planet.get_sprite_index() as f64,
+                0.0,
+                size.width as f64,
+                size.height as f64
+            ]);
+            let sprite = planet.get_sprite();
+            draw_sprite(&mut self.planet_sprite, &sprite, ctx, gl);
+            if planet.get_state() == PlanetState::InPlace {
+                self.done_sprite.set_position(sprite.x, sprite.y);
+                self.done_sprite.draw(ctx.transform, gl);
+            }
+        }
+    }
+
+    fn draw_power_ups(&mut self, power_ups: &PowerUps, ctx: Context, gl: &mut GlGraphics) {
+        let size = self.sizes.power_up;
+        for power_up in power_ups.iter() {
+            self.power_up_sprite.set_src_rect([
+                size.width as f64 * power_up.get_kind().get_frame() as f64,
+                0.0,
+                size.width as f64,
+                size.height as f64
+            ]);
+            draw_sprite(&mut self.power_up_sprite, &power_up.get_sprite(), ctx, gl);
+        }
+    }
+
+    fn draw_player(&mut self, player: &Player, game: &Game, ctx: Context, gl: &mut GlGraphics) {
+        let sprite = player.get_sprite();
+        match player.get_state() {
+            PlayerState::Dying => {
+                draw_shatter(&mut self.player_sprite, &sprite, self.sizes.player, ctx, gl);
+            }
+            PlayerState::Dead => {}
+            _ => {
+                self.player_sprite.set_src_rect([0.0, 0.0, sprite.width, sprite.height]);
+                draw_sprite(&mut self.player_sprite, &sprite, ctx, gl);
+                if player.is_shielded(game) {
+                    let radius = sprite.width.max(sprite.height) * 0.6;
+                    Ellipse::new_border(SHIELD_RING, 2.0)
+                        .draw(ellipse::circle(sprite.x, sprite.y, radius), &ctx.draw_state, ctx.transform, gl);
+                }
+            }
+        }
+    }
+}
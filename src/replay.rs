@@ -0,0 +1,335 @@
+use std::io::{self, ErrorKind, Read, Write};
+use crate::difficulty::Difficulty;
+use crate::high_scores::HighScores;
+use crate::input::{InputFrame, InputSource};
+use crate::world::World;
+
+// Layout, all integers little endian:
+//   magic "PWRP", version u16, seed u64,
+//   the high score table the run started with, as the length u32 and then TOML text
+//   then one record for every tick whose input differs from the tick before:
+//     tick delta from the previous record (LEB128), flags u8,
+//     move x and y as f64 if FLAG_MOVE is set, aim x and y as f64 if FLAG_AIM is set,
+//     the difficulty as u8 if FLAG_DIFFICULTY is set
+const MAGIC: &[u8; 4] = b"PWRP";
+// version 5 adds the high score table, which decides whether a game over asks for initials
+// version 6 lays generated levels out differently, so older recordings no longer line up
+// version 7 adds the difficulty chosen for each game
+// version 8 spawns several kinds of enemy, drawing on the spawn generator for each
+// version 9 steers enemies, so they no longer go where they used to
+// version 10 adds gunners
+// version 11 adds bosses
+// version 12 adds power-ups
+// version 13 keeps the playfield one size whatever the window, and the ship wholly inside it
+// version 14 caps how hard levels get on every difficulty
+// version 15 counts spread shot's lasers against the cap
+// version 16 lets a shot kill a ship towing a planet rather than only knocking the planet loose
+// version 17 drops playfield resizes, now that the playfield is always the same size
+const VERSION: u16 = 17;
+
+const FLAG_FIRE: u8 = 1;
+const FLAG_CONFIRM: u8 = 1 << 1;
+const FLAG_PAUSE: u8 = 1 << 2;
+const FLAG_MOVE: u8 = 1 << 3;
+const FLAG_AIM: u8 = 1 << 4;
+const FLAG_DIFFICULTY: u8 = 1 << 6;
+
+/// An input frame stamped with the tick it was applied before: a frame with tick `n` was
+/// applied once `n` ticks had been stepped.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RecordedFrame {
+    pub tick: u64,
+    pub frame: InputFrame
+}
+
+// confirm, pause and difficulty changes only last for the tick they happen on
+fn held(frame: InputFrame) -> InputFrame {
+    InputFrame { confirm: false, pause: false, difficulty: None, ..frame }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+fn write_varint(writer: &mut impl Write, mut value: u64) -> io::Result<()> {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            return writer.write_all(&[byte]);
+        }
+        writer.write_all(&[byte | 0x80])?;
+    }
+}
+
+fn read_varint(reader: &mut impl Read) -> io::Result<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = read_u8(reader)?;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(invalid_data("tick delta is too long"))
+}
+
+fn read_u8(reader: &mut impl Read) -> io::Result<u8> {
+    let mut buf = [0u8; 1];
+    reader.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_u16(reader: &mut impl Read) -> io::Result<u16> {
+    let mut buf = [0u8; 2];
+    reader.read_exact(&mut buf)?;
+    Ok(u16::from_le_bytes(buf))
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_f64(reader: &mut impl Read) -> io::Result<f64> {
+    Ok(f64::from_bits(read_u64(reader)?))
+}
+
+/// Writes the input for every tick, so a run can be replayed exactly. Only ticks whose input
+/// changed are stored.
+pub struct Recorder<W: Write> {
+    last_frame: InputFrame,
+    last_tick: u64,
+    writer: W
+}
+
+impl<W: Write> Recorder<W> {
+    pub fn new(mut writer: W, seed: u64, high_scores: &HighScores) -> io::Result<Self> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        writer.write_all(&seed.to_le_bytes())?;
+        let high_scores = high_scores.to_toml()?;
+        writer.write_all(&(high_scores.len() as u32).to_le_bytes())?;
+        writer.write_all(high_scores.as_bytes())?;
+        Ok(Self { last_frame: InputFrame::default(), last_tick: 0, writer })
+    }
+
+    pub fn record(&mut self, tick: u64, frame: InputFrame) -> io::Result<()> {
+        if frame == self.last_frame {
+            return Ok(());
+        }
+
+        let moved = (frame.move_x, frame.move_y) != (self.last_frame.move_x, self.last_frame.move_y);
+        let aimed = (frame.aim_x, frame.aim_y) != (self.last_frame.aim_x, self.last_frame.aim_y);
+        let mut flags = 0;
+        if frame.fire { flags |= FLAG_FIRE; }
+        if frame.confirm { flags |= FLAG_CONFIRM; }
+        if frame.pause { flags |= FLAG_PAUSE; }
+        if moved { flags |= FLAG_MOVE; }
+        if aimed { flags |= FLAG_AIM; }
+        if frame.difficulty.is_some() { flags |= FLAG_DIFFICULTY; }
+
+        write_varint(&mut self.writer, tick.saturating_sub(self.last_tick))?;
+        self.writer.write_all(&[flags])?;
+        if moved {
+            self.writer.write_all(&frame.move_x.to_le_bytes())?;
+            self.writer.write_all(&frame.move_y.to_le_bytes())?;
+        }
+        if aimed {
+            self.writer.write_all(&frame.aim_x.to_le_bytes())?;
+            self.writer.write_all(&frame.aim_y.to_le_bytes())?;
+        }
+        if let Some(difficulty) = frame.difficulty {
+            self.writer.write_all(&[difficulty.to_index()])?;
+        }
+
+        self.last_frame = held(frame);
+        self.last_tick = self.last_tick.max(tick);
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// A recording played back as an input source.
+pub struct Replay {
+    current: InputFrame,
+    frames: Vec<RecordedFrame>,
+    high_scores: HighScores,
+    next: usize,
+    seed: u64
+}
+
+impl Replay {
+    pub fn read(mut reader: impl Read) -> io::Result<Self> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not a planet wrangler recording"));
+        }
+
+        let version = read_u16(&mut reader)?;
+        if version != VERSION {
+            return Err(invalid_data(&format!("unsupported recording version {}", version)));
+        }
+
+        let seed = read_u64(&mut reader)?;
+        let mut high_scores = vec![0u8; read_u32(&mut reader)? as usize];
+        reader.read_exact(&mut high_scores)?;
+        let high_scores = String::from_utf8(high_scores).map_err(|_| invalid_data("high scores are not text"))?;
+        let high_scores = HighScores::from_toml(&high_scores)?;
+        let mut frames = Vec::new();
+        let mut frame = InputFrame::default();
+        let mut tick = 0u64;
+        loop {
+            let delta = match read_varint(&mut reader) {
+                Ok(delta) => delta,
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e)
+            };
+            tick += delta;
+            let flags = read_u8(&mut reader)?;
+            frame.fire = flags & FLAG_FIRE != 0;
+            frame.confirm = flags & FLAG_CONFIRM != 0;
+            frame.pause = flags & FLAG_PAUSE != 0;
+            if flags & FLAG_MOVE != 0 {
+                frame.move_x = read_f64(&mut reader)?;
+                frame.move_y = read_f64(&mut reader)?;
+            }
+            if flags & FLAG_AIM != 0 {
+                frame.aim_x = read_f64(&mut reader)?;
+                frame.aim_y = read_f64(&mut reader)?;
+            }
+            frame.difficulty = None;
+            if flags & FLAG_DIFFICULTY != 0 {
+                let index = read_u8(&mut reader)?;
+                frame.difficulty = Some(Difficulty::from_index(index).ok_or_else(|| invalid_data("unknown difficulty"))?);
+            }
+            frames.push(RecordedFrame { tick, frame });
+        }
+
+        Ok(Self { current: InputFrame::default(), frames, high_scores, next: 0, seed })
+    }
+
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The high score table the recorded run started with.
+    pub fn get_high_scores(&self) -> &HighScores {
+        &self.high_scores
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.frames.len()
+    }
+
+    /// The frame that was applied once `tick` ticks had been stepped.
+    pub fn frame_for(&mut self, tick: u64) -> InputFrame {
+        let mut frame = held(self.current);
+        while self.next < self.frames.len() && self.frames[self.next].tick <= tick {
+            frame = self.frames[self.next].frame;
+            self.next += 1;
+        }
+        self.current = frame;
+        frame
+    }
+}
+
+impl InputSource for Replay {
+    fn next_frame(&mut self, world: &World) -> InputFrame {
+        self.frame_for(world.get_tick())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::BotInput;
+    use crate::tuning::Tuning;
+    use crate::world::SpriteSizes;
+
+    // what a run ended with, with enemies in order of where they are since their ids are random
+    fn snapshot(world: &World) -> String {
+        let game = world.get_game();
+        let mut enemies: Vec<(f64, f64)> = world.get_enemies().iter().map(|e| e.get_center()).collect();
+        enemies.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let player = world.get_player().get_sprite();
+        format!(
+            "{} {} {} {:?} {} {:?} {:?}",
+            world.get_tick(), game.score, game.lives, game.state, game.get_level(), (player.x, player.y), enemies
+        )
+    }
+
+    #[test]
+    fn frames_read_back_as_recorded() {
+        let frames = [
+            RecordedFrame { tick: 0, frame: InputFrame { confirm: true, difficulty: Some(Difficulty::Hard), ..InputFrame::default() } },
+            RecordedFrame { tick: 3, frame: InputFrame { move_x: 0.5, move_y: -1.0, ..InputFrame::default() } },
+            RecordedFrame { tick: 400, frame: InputFrame { move_x: 0.5, move_y: -1.0, aim_x: 1.0, fire: true, ..InputFrame::default() } },
+            RecordedFrame { tick: 401, frame: InputFrame { pause: true, ..InputFrame::default() } }
+        ];
+        let mut bytes = Vec::new();
+        let mut recorder = Recorder::new(&mut bytes, 1234, &HighScores::default()).unwrap();
+        for recorded in frames {
+            recorder.record(recorded.tick, recorded.frame).unwrap();
+        }
+        recorder.flush().unwrap();
+
+        let mut replay = Replay::read(bytes.as_slice()).unwrap();
+        assert_eq!(replay.get_seed(), 1234);
+        assert_eq!(replay.frames, frames);
+        assert_eq!(replay.frame_for(0), frames[0].frame);
+        assert_eq!(replay.frame_for(2), held(frames[0].frame));
+        assert_eq!(replay.frame_for(3), frames[1].frame);
+    }
+
+    #[test]
+    fn other_files_and_versions_are_turned_away() {
+        assert!(Replay::read(&b"NOPE"[..]).is_err());
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&(VERSION - 1).to_le_bytes());
+        bytes.extend_from_slice(&0u64.to_le_bytes());
+        assert_eq!(Replay::read(bytes.as_slice()).err().map(|e| e.kind()), Some(ErrorKind::InvalidData));
+    }
+
+    #[test]
+    fn a_replay_plays_the_recorded_run_again() {
+        let seed = 99;
+        let new_world = || World::new(1600.0, 900.0, SpriteSizes::default(), Tuning::default(), seed);
+
+        let mut world = new_world();
+        let mut bot = BotInput::new();
+        let mut bytes = Vec::new();
+        let mut recorder = Recorder::new(&mut bytes, seed, &HighScores::default()).unwrap();
+        for tick in 0..3000 {
+            let mut frame = bot.next_frame(&world);
+            if tick == 0 {
+                frame.difficulty = Some(Difficulty::Hard);
+            }
+            recorder.record(world.get_tick(), frame).unwrap();
+            world.step(frame);
+        }
+        recorder.flush().unwrap();
+        let recorded = snapshot(&world);
+        assert!(world.get_game().score > 0, "the bot should have scored something: {}", recorded);
+
+        let mut replay = Replay::read(bytes.as_slice()).unwrap();
+        let mut replayed = World::new(1600.0, 900.0, SpriteSizes::default(), Tuning::default(), replay.get_seed());
+        replayed.set_high_scores(replay.get_high_scores().clone());
+        while replayed.get_tick() < 3000 {
+            let frame = replay.next_frame(&replayed);
+            replayed.step(frame);
+        }
+        assert_eq!(snapshot(&replayed), recorded);
+    }
+}
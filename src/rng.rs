@@ -0,0 +1,35 @@
+use rand::{ChaChaRng, Rng, SeedableRng};
+
+/// The generator used for everything random in the game. ChaCha gives the same
+/// sequence on every platform, so a seed always reproduces the same run.
+pub type GameRng = ChaChaRng;
+
+// independent streams derived from one seed, so level layouts don't depend on how
+// many enemies were spawned while playing the previous level
+const SPAWN_STREAM: u32 = 0;
+const LAYOUT_STREAM: u32 = 1;
+const RESUME_STREAM: u32 = 2;
+
+fn new_rng(seed: u64, stream: u32, index: u32) -> GameRng {
+    GameRng::from_seed(&[seed as u32, (seed >> 32) as u32, stream, index])
+}
+
+/// A seed for when the player didn't ask for one.
+pub fn random_seed() -> u64 {
+    rand::thread_rng().gen()
+}
+
+/// The generator for gameplay events such as enemy spawns.
+pub fn spawn_rng(seed: u64) -> GameRng {
+    new_rng(seed, SPAWN_STREAM, 0)
+}
+
+/// The generator for gameplay events after continuing a saved game at `tick`.
+pub fn resume_rng(seed: u64, tick: u64) -> GameRng {
+    GameRng::from_seed(&[seed as u32, (seed >> 32) as u32, RESUME_STREAM, tick as u32, (tick >> 32) as u32])
+}
+
+/// The generator for the layout of one level.
+pub fn layout_rng(seed: u64, level: u32) -> GameRng {
+    new_rng(seed, LAYOUT_STREAM, level)
+}
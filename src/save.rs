@@ -0,0 +1,177 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use crate::black_hole::BlackHoles;
+use crate::boss::Arena;
+use crate::enemy::Enemies;
+use crate::game::Game;
+use crate::game_sprite::GameSprite;
+use crate::laser::Lasers;
+use crate::planets::Planets;
+use crate::player::Player;
+use crate::power_up::PowerUps;
+use crate::projectile::Projectiles;
+use crate::settings::data_dir;
+
+const SAVE_FILE: &str = "save.toml";
+// bump whenever a saved type changes, so old saves are turned away instead of misread
+pub(crate) const SAVE_VERSION: u32 = 11;
+
+/// Anything kept in a map keyed by its sprite's id.
+pub(crate) trait HasSprite {
+    fn get_sprite(&self) -> GameSprite;
+}
+
+/// Saves a map keyed by sprite id as a list in id order. Loaded sprites get new ids, handed out
+/// in the same order, so the map iterates just as it did.
+pub(crate) mod sprite_map {
+    use super::*;
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer, T: Serialize>(map: &BTreeMap<Uuid, T>, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(map.values())
+    }
+
+    pub fn deserialize<'de, D, T>(deserializer: D) -> Result<BTreeMap<Uuid, T>, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: Deserialize<'de> + HasSprite
+    {
+        let values = Vec::<T>::deserialize(deserializer)?;
+        Ok(values.into_iter().map(|v| (v.get_sprite().get_id(), v)).collect())
+    }
+}
+
+/// A game in progress, written to the data directory so it can be continued later.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct SaveGame {
+    pub version: u32,
+    pub seed: u64,
+    pub tick: u64,
+    /// Game time, which stands still while paused and so falls behind the tick.
+    pub time: Duration,
+    pub game: Game,
+    pub black_holes: BlackHoles,
+    pub arena: Arena,
+    pub enemies: Enemies,
+    pub lasers: Lasers,
+    pub projectiles: Projectiles,
+    pub planets: Planets,
+    pub player: Player,
+    pub power_ups: PowerUps
+}
+
+impl SaveGame {
+    pub fn default_path() -> Option<PathBuf> {
+        data_dir().map(|dir| dir.join(SAVE_FILE))
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        // check the version before anything else, which may have changed shape
+        #[derive(Deserialize)]
+        struct Version {
+            version: u32
+        }
+        let version: Version = toml::from_str(&text).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+        if version.version != SAVE_VERSION {
+            return Err(io::Error::new(ErrorKind::InvalidData, format!("unsupported save version {}", version.version)));
+        }
+        toml::from_str(&text).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let text = toml::to_string(self).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+        fs::write(path, text)
+    }
+
+    /// Deletes the save, once its game is over.
+    pub fn remove(path: &Path) -> io::Result<()> {
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+            _ => Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::BotInput;
+    use crate::difficulty::Difficulty;
+    use crate::game::GameState;
+    use crate::input::{InputFrame, InputSource};
+    use crate::tuning::Tuning;
+    use crate::world::{SpriteSizes, World};
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("planet-wrangler-{}-{}.toml", name, std::process::id()))
+    }
+
+    fn played_world() -> World {
+        let mut world = World::new(1600.0, 900.0, SpriteSizes::default(), Tuning::default(), 7);
+        world.step(InputFrame { difficulty: Some(Difficulty::Hard), ..InputFrame::default() });
+        let mut bot = BotInput::new();
+        for _ in 0..1500 {
+            let frame = bot.next_frame(&world);
+            world.step(frame);
+        }
+        world
+    }
+
+    // where everything is, since loaded sprites get new ids
+    fn positions(world: &World) -> Vec<(f64, f64)> {
+        world.get_enemies().iter().map(|e| e.get_sprite())
+            .chain(world.get_planets().iter().map(|p| p.get_sprite()))
+            .chain(world.get_black_holes().iter().map(|h| h.get_sprite()))
+            .chain([world.get_player().get_sprite()])
+            .map(|sprite| (sprite.x, sprite.y))
+            .collect()
+    }
+
+    #[test]
+    fn a_saved_game_loads_as_it_was() {
+        let world = played_world();
+        assert_eq!(world.get_game().state, GameState::Running);
+        assert!(world.get_enemies().iter().count() > 0);
+        assert_ne!(world.get_game().tuning, Tuning::default());
+
+        let path = temp_path("save");
+        world.to_save().save(&path).unwrap();
+        let loaded = SaveGame::load(&path);
+        SaveGame::remove(&path).unwrap();
+        assert!(!path.exists());
+
+        let continued = World::from_save(loaded.unwrap(), Tuning::default());
+        let (game, continued_game) = (world.get_game(), continued.get_game());
+        assert_eq!(continued.get_tick(), world.get_tick());
+        assert_eq!(continued.get_clock().now(), world.get_clock().now());
+        assert_eq!(continued.get_seed(), world.get_seed());
+        assert_eq!((continued_game.score, continued_game.lives), (game.score, game.lives));
+        assert_eq!(continued_game.get_level(), game.get_level());
+        assert_eq!(continued_game.state, GameState::Paused);
+        // played on the difficulty it was saved with, not the bare tuning
+        assert_eq!(continued_game.difficulty, Difficulty::Hard);
+        assert_eq!(continued_game.tuning, game.tuning);
+        assert_eq!(continued_game.enemy_kinds, game.enemy_kinds);
+        assert_eq!(positions(&continued), positions(&world));
+    }
+
+    #[test]
+    fn other_versions_are_turned_away() {
+        let mut save = played_world().to_save();
+        save.version = SAVE_VERSION - 1;
+        let path = temp_path("old-save");
+        save.save(&path).unwrap();
+        let loaded = SaveGame::load(&path);
+        SaveGame::remove(&path).unwrap();
+        assert_eq!(loaded.err().map(|e| e.kind()), Some(ErrorKind::InvalidData));
+    }
+}
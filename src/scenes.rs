@@ -0,0 +1,489 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+use std::time::Duration;
+use opengl_graphics::{GlGraphics, GlyphCache};
+use graphics::Context;
+use piston::{Button, Event, Key, ReleaseEvent};
+use planet_wrangler::bindings::Rebinder;
+use planet_wrangler::game::GameState;
+use planet_wrangler::high_scores::{today, HighScores};
+use planet_wrangler::input::{InputFrame, InputSource, LiveInput};
+use planet_wrangler::levels::Campaign;
+use planet_wrangler::menu::Menu;
+use planet_wrangler::replay::Recorder;
+use planet_wrangler::save::SaveGame;
+use planet_wrangler::settings::Settings;
+use planet_wrangler::tuning::Tuning;
+use planet_wrangler::viewport::Viewport;
+use planet_wrangler::world::{World, WorldEvent};
+use crate::renderer::{Renderer, Screen};
+
+/// Everything the scenes share: the world, where its input comes from and the files it's kept in.
+pub struct App {
+    pub campaign: Campaign,
+    /// Whether the game being played was continued from the save, so ending it ends the save too.
+    pub continued_from_save: bool,
+    pub has_save: bool,
+    pub high_scores: HighScores,
+    pub high_scores_path: Option<PathBuf>,
+    pub input: Box<dyn InputSource>,
+    /// Whether someone is playing, rather than watching a replay or the bot.
+    pub live: bool,
+    pub names: HashMap<u32, String>,
+    pub recorder: Option<Recorder<BufWriter<File>>>,
+    pub save_path: Option<PathBuf>,
+    pub settings: Settings,
+    pub settings_path: Option<PathBuf>,
+    /// Starts a game on the next step, as if confirm had been pressed.
+    pub start_pending: bool,
+    pub tuning: Tuning,
+    pub world: World
+}
+
+impl App {
+    /// Steps the world a tick with `frame`, recording it, and returns whatever the world asked
+    /// for that the scenes have to deal with.
+    pub fn step_world(&mut self, mut frame: InputFrame) -> Vec<WorldEvent> {
+        if std::mem::take(&mut self.start_pending) {
+            frame.confirm = true;
+            frame.difficulty = Some(self.settings.difficulty);
+        }
+        if let Some(r) = self.recorder.as_mut() {
+            if let Err(error) = r.record(self.world.get_tick(), frame) {
+                eprintln!("stopped recording: {}", error);
+                self.recorder = None;
+            }
+        }
+        self.world.step(frame);
+        self.keep_high_scores();
+
+        let mut events = self.world.take_events();
+        events.retain(|event| match event {
+            WorldEvent::QuitToTitle(save) => {
+                // the save holds this game now, and whatever's played next is a new one
+                self.continued_from_save = false;
+                if let Some(path) = self.save_path.as_deref() {
+                    match save.save(path) {
+                        Ok(()) => self.has_save = self.recorder.is_none(),
+                        Err(error) => eprintln!("could not save the game: {}", error)
+                    }
+                }
+                false
+            }
+            _ => true
+        });
+        events
+    }
+
+    /// Saves the world's high scores when a new one has gone in.
+    pub fn keep_high_scores(&mut self) {
+        if self.world.get_high_scores() != &self.high_scores {
+            self.high_scores = self.world.get_high_scores().clone();
+            if let Some(path) = self.high_scores_path.as_deref() {
+                if let Err(error) = self.high_scores.save(path) {
+                    eprintln!("could not save high scores: {}", error);
+                }
+            }
+        }
+    }
+
+    /// Swaps the world for the saved game, if there is one that can be read.
+    fn continue_saved(&mut self) -> bool {
+        self.has_save = false;
+        match self.save_path.as_deref().map(SaveGame::load) {
+            Some(Ok(save)) => {
+                self.world = World::from_save(save, self.tuning.clone());
+                self.world.set_campaign(self.campaign.clone());
+                self.world.set_high_scores(self.high_scores.clone());
+                self.world.set_date(today());
+                self.continued_from_save = true;
+                true
+            }
+            Some(Err(error)) => {
+                eprintln!("could not continue the saved game: {}", error);
+                false
+            }
+            None => false
+        }
+    }
+
+    /// Keeps the bindings a rebinding ended with, and plays with them from now on.
+    fn finish_rebinding(&mut self, rebinder: &Rebinder) {
+        let bindings = rebinder.get_bindings().clone();
+        match rebinder.get_controller_id().and_then(|id| self.names.get(&id)) {
+            Some(name) => { self.settings.bindings.profiles.insert(name.clone(), bindings); }
+            None => self.settings.bindings.default = bindings
+        }
+        self.save_settings();
+        self.input = Box::new(LiveInput::new(self.settings.bindings.clone(), self.names.clone()));
+    }
+
+    fn save_settings(&self) {
+        if let Some(path) = self.settings_path.as_deref() {
+            if let Err(error) = self.settings.save(path) {
+                eprintln!("could not save settings: {}", error);
+            }
+        }
+    }
+
+    /// Whether `event` asks to continue the saved game, with C or a controller's continue button.
+    fn is_continue_request(&self, event: &Event) -> bool {
+        match event.release_args() {
+            Some(Button::Keyboard(Key::C)) => true,
+            Some(Button::Controller(button)) => {
+                let bindings = self.settings.bindings.for_controller(self.names.get(&button.id).map(String::as_str));
+                button.button == bindings.continue_button
+            }
+            _ => false
+        }
+    }
+
+    /// Whether `event` asks for the rebinding screen, with F1 or a controller's rebind button.
+    /// Gives the controller to rebind, or `None` for whichever one is touched first.
+    fn rebind_request(&self, event: &Event) -> Option<Option<u32>> {
+        match event.release_args() {
+            Some(Button::Keyboard(Key::F1)) => Some(None),
+            Some(Button::Controller(button)) => {
+                let bindings = self.settings.bindings.for_controller(self.names.get(&button.id).map(String::as_str));
+                (button.button == bindings.rebind_button).then_some(Some(button.id))
+            }
+            _ => None
+        }
+    }
+
+    fn rebind_scene(&self, controller_id: Option<u32>) -> Box<dyn Scene> {
+        let current = self.settings.bindings.for_controller(controller_id.and_then(|id| self.names.get(&id)).map(String::as_str));
+        Box::new(RebindScene { rebinder: Rebinder::new(controller_id, current) })
+    }
+}
+
+/// What the scene stack should do next.
+pub enum Transition {
+    None,
+    Push(Box<dyn Scene>),
+    Pop,
+    Replace(Box<dyn Scene>),
+    Quit
+}
+
+/// One screen of the game. Only the scene on top of the stack gets events, ticks and draws.
+pub trait Scene {
+    fn handle_event(&mut self, event: &Event, app: &mut App) -> Transition {
+        app.input.handle_event(event);
+        Transition::None
+    }
+
+    /// Runs one fixed tick. `now` is real time, for menus.
+    fn tick(&mut self, app: &mut App, now: Duration) -> Transition;
+
+    fn draw(
+        &mut self, app: &App, renderer: &mut Renderer, viewport: &Viewport,
+        glyphs: &mut GlyphCache, ctx: Context, gl: &mut GlGraphics
+    );
+}
+
+/// The scene the game opens on: the title screen for players, straight into the game otherwise.
+pub fn first_scene(app: &App) -> Box<dyn Scene> {
+    if app.live {
+        Box::new(TitleScene::new(app))
+    } else {
+        Box::new(GameplayScene)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum TitleItem {
+    Start,
+    Continue,
+    Difficulty,
+    HighScores,
+    Options,
+    Quit
+}
+
+impl TitleItem {
+    fn label(self, app: &App) -> String {
+        match self {
+            TitleItem::Start => "Start".to_string(),
+            TitleItem::Continue => "Continue".to_string(),
+            TitleItem::Difficulty => format!("Difficulty: {}", app.settings.difficulty.get_name()),
+            TitleItem::HighScores => "High Scores".to_string(),
+            TitleItem::Options => "Options".to_string(),
+            TitleItem::Quit => "Quit".to_string()
+        }
+    }
+}
+
+pub struct TitleScene {
+    items: Vec<TitleItem>,
+    menu: Menu
+}
+
+impl TitleScene {
+    pub fn new(app: &App) -> Self {
+        let items: Vec<TitleItem> = [
+            TitleItem::Start, TitleItem::Continue, TitleItem::Difficulty, TitleItem::HighScores,
+            TitleItem::Options, TitleItem::Quit
+        ]
+            .into_iter()
+            .filter(|&item| item != TitleItem::Continue || app.has_save)
+            .collect();
+        let labels: Vec<String> = items.iter().map(|item| item.label(app)).collect();
+        let labels: Vec<&str> = labels.iter().map(String::as_str).collect();
+        Self { items, menu: Menu::new(&labels) }
+    }
+
+    fn continue_saved(app: &mut App) -> Transition {
+        if app.continue_saved() {
+            Transition::Push(Box::new(GameplayScene))
+        } else {
+            Transition::None
+        }
+    }
+}
+
+impl Scene for TitleScene {
+    fn handle_event(&mut self, event: &Event, app: &mut App) -> Transition {
+        if event.release_args() == Some(Button::Keyboard(Key::Escape)) {
+            Transition::Quit
+        } else if app.has_save && app.is_continue_request(event) {
+            Self::continue_saved(app)
+        } else if let Some(id) = app.rebind_request(event) {
+            Transition::Push(app.rebind_scene(id))
+        } else {
+            app.input.handle_event(event);
+            Transition::None
+        }
+    }
+
+    fn tick(&mut self, app: &mut App, now: Duration) -> Transition {
+        // the saved game comes and goes as games are quit and continued
+        if self.items.contains(&TitleItem::Continue) != app.has_save {
+            *self = Self::new(app);
+        }
+        let frame = app.input.next_frame(&app.world);
+        match self.menu.update(&frame, now).map(|i| self.items[i]) {
+            Some(TitleItem::Start) => {
+                app.start_pending = true;
+                Transition::Push(Box::new(GameplayScene))
+            }
+            Some(TitleItem::Continue) => Self::continue_saved(app),
+            Some(TitleItem::Difficulty) => {
+                app.settings.difficulty = app.settings.difficulty.next();
+                app.save_settings();
+                self.menu.set_item(self.menu.get_selected(), &TitleItem::Difficulty.label(app));
+                Transition::None
+            }
+            Some(TitleItem::HighScores) => Transition::Push(Box::new(HighScoresScene)),
+            Some(TitleItem::Options) => Transition::Push(Box::new(OptionsScene::new())),
+            Some(TitleItem::Quit) => Transition::Quit,
+            None => Transition::None
+        }
+    }
+
+    fn draw(
+        &mut self, _app: &App, renderer: &mut Renderer, viewport: &Viewport,
+        glyphs: &mut GlyphCache, ctx: Context, gl: &mut GlGraphics
+    ) {
+        renderer.draw_screen(Screen { title: "Planet Wrangler", lines: &[], menu: Some(&self.menu) }, viewport, glyphs, ctx, gl);
+    }
+}
+
+const SET_UP_CONTROLLER: usize = 0;
+
+pub struct OptionsScene {
+    menu: Menu
+}
+
+impl OptionsScene {
+    pub fn new() -> Self {
+        Self { menu: Menu::new(&["Set Up Controller", "Back"]) }
+    }
+}
+
+impl Scene for OptionsScene {
+    fn tick(&mut self, app: &mut App, now: Duration) -> Transition {
+        let frame = app.input.next_frame(&app.world);
+        match self.menu.update(&frame, now) {
+            Some(SET_UP_CONTROLLER) => Transition::Push(app.rebind_scene(None)),
+            Some(_) => Transition::Pop,
+            None if frame.pause => Transition::Pop,
+            None => Transition::None
+        }
+    }
+
+    fn draw(
+        &mut self, _app: &App, renderer: &mut Renderer, viewport: &Viewport,
+        glyphs: &mut GlyphCache, ctx: Context, gl: &mut GlGraphics
+    ) {
+        renderer.draw_screen(Screen { title: "Options", lines: &[], menu: Some(&self.menu) }, viewport, glyphs, ctx, gl);
+    }
+}
+
+pub struct HighScoresScene;
+
+impl Scene for HighScoresScene {
+    fn tick(&mut self, app: &mut App, _now: Duration) -> Transition {
+        let frame = app.input.next_frame(&app.world);
+        if frame.confirm || frame.pause {
+            Transition::Pop
+        } else {
+            Transition::None
+        }
+    }
+
+    fn draw(
+        &mut self, app: &App, renderer: &mut Renderer, viewport: &Viewport,
+        glyphs: &mut GlyphCache, ctx: Context, gl: &mut GlGraphics
+    ) {
+        let mut lines: Vec<String> = app.high_scores.iter().enumerate()
+            .map(|(i, high_score)| format!(
+                "{:>2}. {} {:>8}  Level {:<3} {}",
+                i + 1, high_score.initials, high_score.score, high_score.level, high_score.date
+            ))
+            .collect();
+        if lines.is_empty() {
+            lines.push("No scores yet".to_string());
+        }
+        renderer.draw_screen(Screen { title: "High Scores", lines: &lines, menu: None }, viewport, glyphs, ctx, gl);
+    }
+}
+
+/// Asks for each binding in turn, over whatever scene opened it.
+pub struct RebindScene {
+    rebinder: Rebinder
+}
+
+impl Scene for RebindScene {
+    fn handle_event(&mut self, event: &Event, app: &mut App) -> Transition {
+        self.rebinder.handle_event(event);
+        if self.rebinder.is_finished() {
+            app.finish_rebinding(&self.rebinder);
+            Transition::Pop
+        } else if event.release_args() == Some(Button::Keyboard(Key::F1)) {
+            Transition::Pop
+        } else {
+            Transition::None
+        }
+    }
+
+    fn tick(&mut self, _app: &mut App, _now: Duration) -> Transition {
+        Transition::None
+    }
+
+    fn draw(
+        &mut self, _app: &App, renderer: &mut Renderer, viewport: &Viewport,
+        glyphs: &mut GlyphCache, ctx: Context, gl: &mut GlGraphics
+    ) {
+        let lines = [self.rebinder.get_prompt().to_string(), "F1 cancels".to_string()];
+        renderer.draw_screen(Screen { title: "Set Up Controller", lines: &lines, menu: None }, viewport, glyphs, ctx, gl);
+    }
+}
+
+/// The game itself, including its pause menu and the screens between lives and levels.
+pub struct GameplayScene;
+
+impl Scene for GameplayScene {
+    fn handle_event(&mut self, event: &Event, app: &mut App) -> Transition {
+        // players pause with Esc, but there's nothing to pause while watching
+        if !app.live && event.release_args() == Some(Button::Keyboard(Key::Escape)) {
+            return Transition::Quit;
+        }
+        app.input.handle_event(event);
+        Transition::None
+    }
+
+    fn tick(&mut self, app: &mut App, _now: Duration) -> Transition {
+        let frame = app.input.next_frame(&app.world);
+        let events = app.step_world(frame);
+        if app.live && events.iter().any(|event| matches!(event, WorldEvent::OpenSettings)) {
+            return Transition::Push(app.rebind_scene(None));
+        }
+        match app.world.get_game().state {
+            GameState::Over => Transition::Replace(Box::new(GameOverScene::new())),
+            // quit from the pause menu
+            GameState::Starting if app.live => Transition::Pop,
+            _ => Transition::None
+        }
+    }
+
+    fn draw(
+        &mut self, app: &App, renderer: &mut Renderer, viewport: &Viewport,
+        glyphs: &mut GlyphCache, ctx: Context, gl: &mut GlGraphics
+    ) {
+        renderer.draw_world(&app.world, viewport, glyphs, ctx, gl);
+    }
+}
+
+const PLAY_AGAIN: usize = 0;
+
+/// The end of a game: initials for a new high score, then a choice of playing again.
+pub struct GameOverScene {
+    menu: Menu
+}
+
+impl GameOverScene {
+    pub fn new() -> Self {
+        Self { menu: Menu::new(&["Play Again", "Title"]) }
+    }
+}
+
+impl Scene for GameOverScene {
+    fn handle_event(&mut self, event: &Event, app: &mut App) -> Transition {
+        GameplayScene.handle_event(event, app)
+    }
+
+    fn tick(&mut self, app: &mut App, now: Duration) -> Transition {
+        let mut frame = app.input.next_frame(&app.world);
+        if !app.live {
+            // the replay or the bot starts the next game itself
+            app.step_world(frame);
+            return match app.world.get_game().state {
+                GameState::Over => Transition::None,
+                _ => Transition::Replace(Box::new(GameplayScene))
+            };
+        }
+
+        let chosen = if app.world.get_initials_entry().is_none() {
+            let chosen = self.menu.update(&frame, now);
+            // the menu decides what confirm does, rather than the world
+            frame.confirm = false;
+            chosen
+        } else {
+            None
+        };
+        app.step_world(frame);
+        match chosen {
+            Some(PLAY_AGAIN) => {
+                app.start_pending = true;
+                Transition::Replace(Box::new(GameplayScene))
+            }
+            Some(_) => Transition::Pop,
+            None => Transition::None
+        }
+    }
+
+    fn draw(
+        &mut self, app: &App, renderer: &mut Renderer, viewport: &Viewport,
+        glyphs: &mut GlyphCache, ctx: Context, gl: &mut GlGraphics
+    ) {
+        let world = &app.world;
+        let score = format!("Score: {}   Level: {}", world.get_game().score, world.get_game().get_level());
+        match world.get_initials_entry() {
+            Some(entry) => {
+                let initials: String = entry.get_initials().chars().enumerate()
+                    .map(|(i, c)| if i == entry.get_position() { format!("[{}]", c) } else { format!(" {} ", c) })
+                    .collect();
+                let lines = [score, "Enter your initials".to_string(), String::new(), initials];
+                renderer.draw_screen(Screen { title: "New High Score!", lines: &lines, menu: None }, viewport, glyphs, ctx, gl);
+            }
+            None => {
+                let lines = [score, format!("Seed: {}", world.get_seed())];
+                let menu = app.live.then_some(&self.menu);
+                renderer.draw_screen(Screen { title: "Game Over", lines: &lines, menu }, viewport, glyphs, ctx, gl);
+            }
+        }
+    }
+}
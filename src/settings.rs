@@ -0,0 +1,100 @@
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use crate::bindings::Bindings;
+use crate::difficulty::Difficulty;
+
+const SETTINGS_FILE: &str = "settings.toml";
+
+/// Where the game keeps its files, e.g. `~/.config/planet-wrangler` on Linux.
+pub fn config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("planet-wrangler"))
+}
+
+/// Where the game keeps its records, e.g. `~/.local/share/planet-wrangler` on Linux.
+pub fn data_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("planet-wrangler"))
+}
+
+/// Reads a TOML file, falling back to the defaults when it doesn't exist yet.
+pub(crate) fn load_toml<T: DeserializeOwned + Default>(path: &Path) -> io::Result<T> {
+    match fs::read_to_string(path) {
+        Ok(text) => toml::from_str(&text).map_err(|e| io::Error::new(ErrorKind::InvalidData, e)),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(T::default()),
+        Err(e) => Err(e)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DisplayMode {
+    Windowed,
+    #[default]
+    Fullscreen,
+    /// A window without decorations covering the whole desktop.
+    Borderless
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct DisplaySettings {
+    pub mode: DisplayMode,
+    /// The window size in windowed mode.
+    pub width: u32,
+    pub height: u32,
+    pub vsync: bool,
+    /// Frames drawn per second, at most.
+    pub fps: u64
+}
+
+impl Default for DisplaySettings {
+    fn default() -> Self {
+        Self { mode: DisplayMode::Fullscreen, width: 1000, height: 1000, vsync: false, fps: 60 }
+    }
+}
+
+impl DisplaySettings {
+    // turns away a window no one could see, or no frames at all
+    fn validate(&self) -> io::Result<()> {
+        if self.width == 0 || self.height == 0 {
+            return Err(io::Error::new(ErrorKind::InvalidData, "display width and height must be at least 1"));
+        }
+        if self.fps == 0 {
+            return Err(io::Error::new(ErrorKind::InvalidData, "display fps must be at least 1"));
+        }
+        Ok(())
+    }
+}
+
+/// Player preferences, kept in a TOML file.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct Settings {
+    pub bindings: Bindings,
+    pub display: DisplaySettings,
+    /// The difficulty new games start on.
+    pub difficulty: Difficulty
+}
+
+impl Settings {
+    pub fn default_path() -> Option<PathBuf> {
+        config_dir().map(|dir| dir.join(SETTINGS_FILE))
+    }
+
+    /// Reads the settings, falling back to the defaults when the file doesn't exist yet.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let settings: Self = load_toml(path)?;
+        settings.display.validate()?;
+        Ok(settings)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let text = toml::to_string_pretty(self).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+        fs::write(path, text)
+    }
+}
@@ -0,0 +1,103 @@
+use std::f64::consts::{PI, TAU};
+use crate::rect::Rect;
+
+// spreads enemies sitting exactly on top of each other in different directions
+const GOLDEN_ANGLE: f64 = 2.399_963_229_728_653;
+
+pub fn center(r: &Rect) -> (f64, f64) {
+    (r.x as f64 + r.w as f64 / 2.0, r.y as f64 + r.h as f64 / 2.0)
+}
+
+pub fn length((x, y): (f64, f64)) -> f64 {
+    x.hypot(y)
+}
+
+/// The vector at most `max` long, pointing the same way.
+pub fn truncate(v: (f64, f64), max: f64) -> (f64, f64) {
+    let l = length(v);
+    if l > max { (v.0 / l * max, v.1 / l * max) } else { v }
+}
+
+/// The direction from `from` to `to`, one unit long, or nothing when they're in the same place.
+pub fn towards(from: (f64, f64), to: (f64, f64)) -> (f64, f64) {
+    let (x, y) = (to.0 - from.0, to.1 - from.1);
+    let l = x.hypot(y);
+    if l < f64::EPSILON { (0.0, 0.0) } else { (x / l, y / l) }
+}
+
+/// Pushes away from neighbors closer than `radius`, harder the closer they are. `index` is
+/// this enemy's own place in `neighbors`.
+pub fn separation(position: (f64, f64), index: usize, neighbors: &[(f64, f64)], radius: f64) -> (f64, f64) {
+    let mut force = (0.0, 0.0);
+    for (i, &other) in neighbors.iter().enumerate() {
+        if i == index {
+            continue;
+        }
+        let distance = length((position.0 - other.0, position.1 - other.1));
+        if distance >= radius {
+            continue;
+        }
+        let away = match towards(other, position) {
+            (0.0, 0.0) => {
+                let angle = (i as f64 - index as f64) * GOLDEN_ANGLE;
+                (angle.cos(), angle.sin())
+            }
+            away => away
+        };
+        let strength = 1.0 - distance / radius;
+        force = (force.0 + away.0 * strength, force.1 + away.1 * strength);
+    }
+    truncate(force, 1.0)
+}
+
+/// Pulls towards the middle of the neighbors within `radius`, harder the further off it is.
+pub fn cohesion(position: (f64, f64), index: usize, neighbors: &[(f64, f64)], radius: f64) -> (f64, f64) {
+    let (mut sum, mut count) = ((0.0, 0.0), 0);
+    for (i, &other) in neighbors.iter().enumerate() {
+        if i != index && length((position.0 - other.0, position.1 - other.1)) < radius {
+            sum = (sum.0 + other.0, sum.1 + other.1);
+            count += 1;
+        }
+    }
+    if count == 0 {
+        return (0.0, 0.0);
+    }
+    let middle = (sum.0 / count as f64, sum.1 / count as f64);
+    let (x, y) = towards(position, middle);
+    let strength = length((middle.0 - position.0, middle.1 - position.1)) / radius;
+    (x * strength, y * strength)
+}
+
+/// Pushes away from any obstacle whose edge is closer than `radius`.
+pub fn avoidance(position: (f64, f64), obstacles: &[Rect], radius: f64) -> (f64, f64) {
+    let mut force = (0.0, 0.0);
+    for obstacle in obstacles {
+        let middle = center(obstacle);
+        let reach = radius + (obstacle.w.max(obstacle.h) as f64) / 2.0;
+        let distance = length((position.0 - middle.0, position.1 - middle.1));
+        if distance >= reach {
+            continue;
+        }
+        let away = towards(middle, position);
+        let strength = 1.0 - distance / reach;
+        force = (force.0 + away.0 * strength, force.1 + away.1 * strength);
+    }
+    truncate(force, 1.0)
+}
+
+/// Turns `velocity` towards `desired` by at most `max_turn` radians and sets its speed to
+/// `desired`'s. Something standing still sets off straight in the direction it wants.
+pub fn turn(velocity: (f64, f64), desired: (f64, f64), max_turn: f64) -> (f64, f64) {
+    let speed = length(desired);
+    if speed < f64::EPSILON {
+        return (0.0, 0.0);
+    }
+    if length(velocity) < f64::EPSILON {
+        return desired;
+    }
+    let heading = velocity.1.atan2(velocity.0);
+    let wanted = desired.1.atan2(desired.0);
+    let difference = (wanted - heading + PI).rem_euclid(TAU) - PI;
+    let angle = heading + difference.clamp(-max_turn, max_turn);
+    (angle.cos() * speed, angle.sin() * speed)
+}
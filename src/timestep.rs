@@ -0,0 +1,34 @@
+pub const TICKS_PER_SECOND: u32 = 120;
+/// Length of one simulation tick in seconds. Every `World::step` advances the game by exactly this much.
+pub const TICK_SECONDS: f64 = 1.0 / TICKS_PER_SECOND as f64;
+// don't try to catch up on more than a quarter of a second, e.g. after the window was dragged
+const MAX_TICKS_PER_ADVANCE: u32 = TICKS_PER_SECOND / 4;
+
+/// Turns real elapsed time into a whole number of fixed ticks, carrying the remainder
+/// over to the next call, so the simulation runs at the same speed whatever the frame rate.
+#[derive(Default)]
+pub struct FixedTimestep {
+    accumulator: f64
+}
+
+impl FixedTimestep {
+    pub fn new() -> Self {
+        Self { accumulator: 0.0 }
+    }
+
+    /// Adds `elapsed` seconds and returns how many ticks are now due.
+    pub fn advance(&mut self, elapsed: f64) -> u32 {
+        self.accumulator += elapsed.max(0.0);
+        let mut ticks = 0;
+        while self.accumulator >= TICK_SECONDS {
+            self.accumulator -= TICK_SECONDS;
+            ticks += 1;
+        }
+
+        if ticks > MAX_TICKS_PER_ADVANCE {
+            ticks = MAX_TICKS_PER_ADVANCE;
+            self.accumulator = 0.0;
+        }
+        ticks
+    }
+}
@@ -0,0 +1,205 @@
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use crate::settings::{config_dir, load_toml};
+
+const TUNING_FILE: &str = "tuning.toml";
+
+/// Gameplay balance, read from a TOML file so it can be changed without recompiling. Any value
+/// left out of the file keeps its default. Speeds are in units per second.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct Tuning {
+    pub player_speed: f64,
+    pub player_die_speed: f64,
+    pub starting_lives: u32,
+    pub starting_black_holes: u32,
+    pub enemy_speed: f64,
+    pub enemy_die_speed: f64,
+    pub max_enemies: usize,
+    /// How long enemies wait after a level starts before they spawn.
+    pub enemy_spawn_delay_millis: u64,
+    pub enemy_spawn_interval_millis: u64,
+    /// How fast enemies can turn, in radians per second.
+    pub enemy_turn_rate: f64,
+    /// Enemies closer than this to each other push apart, as hard as `enemy_separation` says.
+    pub enemy_separation_radius: f64,
+    pub enemy_separation: f64,
+    /// Enemies closer than this to each other pull together, as hard as `enemy_cohesion` says.
+    pub enemy_cohesion_radius: f64,
+    pub enemy_cohesion: f64,
+    /// Enemies closer than this to a planet steer around it, as hard as `enemy_avoidance` says.
+    pub enemy_avoidance_radius: f64,
+    pub enemy_avoidance: f64,
+    /// How long gunners take aim before they fire.
+    pub gunner_aim_millis: u64,
+    pub gunner_reload_millis: u64,
+    pub projectile_speed: f64,
+    /// How long enemy shots last before they fizzle out.
+    pub projectile_lifetime_millis: u64,
+    /// A boss every this many levels, or never when 0.
+    pub boss_levels: u32,
+    pub boss_hit_points: u32,
+    pub boss_speed: f64,
+    pub boss_fire_interval_millis: u64,
+    /// The bonus for beating a boss, which also gives an extra life.
+    pub boss_points: u32,
+    /// A power-up turns up somewhere every this often, as well as those destroyed enemies drop.
+    pub power_up_spawn_interval_millis: u64,
+    /// The chance, from 0 to 1, that a destroyed enemy drops a power-up.
+    pub power_up_drop_chance: f64,
+    /// Power-ups waiting on the playfield at once.
+    pub max_power_ups: usize,
+    /// How long a power-up waits to be picked up.
+    pub power_up_lifetime_millis: u64,
+    /// How long shield, spread shot, rapid fire and speed boost last.
+    pub power_up_duration_millis: u64,
+    /// How far either side of the aim spread shot's extra lasers go, in degrees.
+    pub spread_degrees: f64,
+    /// The laser fire delay during rapid fire, which also lifts the limit on lasers on screen.
+    pub rapid_fire_delay_millis: u64,
+    /// How many times faster the speed boost makes the ship.
+    pub speed_boost: f64,
+    pub laser_speed: f64,
+    /// Lasers on screen at once.
+    pub max_lasers: usize,
+    pub laser_fire_delay_millis: u64,
+    pub enemy_points: u32,
+    pub black_hole_points: u32,
+    /// How much harder enemies get with each level, as a fraction of the first level on normal.
+    pub level_scaling: f64,
+    /// The most times harder than the first level that later levels get.
+    pub max_level_scaling: f64
+}
+
+impl Default for Tuning {
+    fn default() -> Self {
+        Self {
+            player_speed: 300.0,
+            player_die_speed: 150.0,
+            starting_lives: 3,
+            starting_black_holes: 3,
+            enemy_speed: 37.5,
+            enemy_die_speed: 150.0,
+            max_enemies: 75,
+            enemy_spawn_delay_millis: 2000,
+            enemy_spawn_interval_millis: 500,
+            enemy_turn_rate: 4.0,
+            enemy_separation_radius: 48.0,
+            enemy_separation: 1.5,
+            enemy_cohesion_radius: 160.0,
+            enemy_cohesion: 0.2,
+            enemy_avoidance_radius: 32.0,
+            enemy_avoidance: 2.0,
+            gunner_aim_millis: 750,
+            gunner_reload_millis: 3000,
+            projectile_speed: 250.0,
+            projectile_lifetime_millis: 3000,
+            boss_levels: 5,
+            boss_hit_points: 40,
+            boss_speed: 40.0,
+            boss_fire_interval_millis: 1500,
+            boss_points: 2000,
+            power_up_spawn_interval_millis: 15000,
+            power_up_drop_chance: 0.05,
+            max_power_ups: 3,
+            power_up_lifetime_millis: 10000,
+            power_up_duration_millis: 8000,
+            spread_degrees: 15.0,
+            rapid_fire_delay_millis: 40,
+            speed_boost: 1.5,
+            laser_speed: 150.0,
+            max_lasers: 10,
+            laser_fire_delay_millis: 100,
+            enemy_points: 10,
+            black_hole_points: 100,
+            level_scaling: 0.1,
+            max_level_scaling: 3.0
+        }
+    }
+}
+
+impl Tuning {
+    pub fn default_path() -> Option<PathBuf> {
+        config_dir().map(|dir| dir.join(TUNING_FILE))
+    }
+
+    /// Reads the tuning, falling back to the defaults when the file doesn't exist.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let tuning: Self = load_toml(path)?;
+        tuning.validate()?;
+        Ok(tuning)
+    }
+
+    // turns away values the game can't be played with, such as no lives or nothing to cover
+    fn validate(&self) -> io::Result<()> {
+        let invalid = |message: &str| Err(io::Error::new(ErrorKind::InvalidData, message.to_string()));
+        if self.starting_lives == 0 {
+            return invalid("starting_lives must be at least 1");
+        }
+        if self.starting_black_holes == 0 {
+            return invalid("starting_black_holes must be at least 1");
+        }
+        if self.max_enemies == 0 || self.max_lasers == 0 {
+            return invalid("max_enemies and max_lasers must be at least 1");
+        }
+        let intervals = [
+            ("enemy_spawn_interval_millis", self.enemy_spawn_interval_millis),
+            ("gunner_reload_millis", self.gunner_reload_millis),
+            ("boss_fire_interval_millis", self.boss_fire_interval_millis),
+            ("power_up_spawn_interval_millis", self.power_up_spawn_interval_millis)
+        ];
+        if let Some((name, _)) = intervals.iter().find(|(_, millis)| *millis == 0) {
+            return invalid(&format!("{} must be more than 0", name));
+        }
+        if !(0.0..=1.0).contains(&self.power_up_drop_chance) {
+            return invalid("power_up_drop_chance must be between 0 and 1");
+        }
+        Ok(())
+    }
+
+    pub fn get_enemy_spawn_delay(&self) -> Duration {
+        Duration::from_millis(self.enemy_spawn_delay_millis)
+    }
+
+    pub fn get_enemy_spawn_interval(&self) -> Duration {
+        Duration::from_millis(self.enemy_spawn_interval_millis)
+    }
+
+    pub fn get_gunner_aim_time(&self) -> Duration {
+        Duration::from_millis(self.gunner_aim_millis)
+    }
+
+    pub fn get_gunner_reload(&self) -> Duration {
+        Duration::from_millis(self.gunner_reload_millis)
+    }
+
+    pub fn get_projectile_lifetime(&self) -> Duration {
+        Duration::from_millis(self.projectile_lifetime_millis)
+    }
+
+    pub fn get_boss_fire_interval(&self) -> Duration {
+        Duration::from_millis(self.boss_fire_interval_millis)
+    }
+
+    pub fn get_power_up_spawn_interval(&self) -> Duration {
+        Duration::from_millis(self.power_up_spawn_interval_millis)
+    }
+
+    pub fn get_power_up_lifetime(&self) -> Duration {
+        Duration::from_millis(self.power_up_lifetime_millis)
+    }
+
+    pub fn get_power_up_duration(&self) -> Duration {
+        Duration::from_millis(self.power_up_duration_millis)
+    }
+
+    pub fn get_laser_fire_delay(&self) -> Duration {
+        Duration::from_millis(self.laser_fire_delay_millis)
+    }
+
+    pub fn get_rapid_fire_delay(&self) -> Duration {
+        Duration::from_millis(self.rapid_fire_delay_millis)
+    }
+}
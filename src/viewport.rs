@@ -0,0 +1,54 @@
+/// The height of the logical view everything is laid out in, HUD included. The renderer scales
+/// it to fit the window, so every display plays the same game.
+pub const VIEW_HEIGHT: f64 = 1000.0;
+/// The width of the logical view. Windows of any other aspect ratio get black bars.
+pub const VIEW_WIDTH: f64 = 1600.0;
+
+/// Maps the logical view onto a window: scaled uniformly to fit and centered, with black bars
+/// filling whatever is left over.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    offset_x: f64,
+    offset_y: f64,
+    scale: f64,
+    window_height: f64,
+    window_width: f64
+}
+
+impl Viewport {
+    pub fn fit(window_width: f64, window_height: f64) -> Self {
+        let mut scale = (window_width / VIEW_WIDTH).min(window_height / VIEW_HEIGHT);
+        if scale <= 0.0 || !scale.is_finite() {
+            // a minimised window, which shows nothing anyway
+            scale = 1.0;
+        }
+        Self {
+            offset_x: (window_width - VIEW_WIDTH * scale) / 2.0,
+            offset_y: (window_height - VIEW_HEIGHT * scale) / 2.0,
+            scale,
+            window_height,
+            window_width
+        }
+    }
+
+    pub fn get_offset(&self) -> (f64, f64) {
+        (self.offset_x, self.offset_y)
+    }
+
+    pub fn get_window_size(&self) -> (f64, f64) {
+        (self.window_width, self.window_height)
+    }
+
+    /// Window units per logical unit.
+    pub fn get_scale(&self) -> f64 {
+        self.scale
+    }
+
+    /// Converts a window position, such as the mouse cursor, into the logical view.
+    pub fn to_logical(&self, position: [f64; 2]) -> [f64; 2] {
+        [
+            (position[0] - self.offset_x) / self.scale,
+            (position[1] - self.offset_y) / self.scale
+        ]
+    }
+}
@@ -0,0 +1,856 @@
+use std::time::Duration;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use crate::black_hole::{BlackHoles, BlackHoleState};
+use crate::boss::{Arena, BossState, BOSS_FRAMES};
+use crate::clock::{Clock, ManualClock};
+use crate::difficulty::Difficulty;
+use crate::enemy::{Enemies, EnemyKind, EnemyState, ENEMY_FRAMES, ENEMY_SHEETS};
+use crate::game::{Game, GameState};
+use crate::game_sprite::GameSprite;
+use crate::high_scores::{HighScore, HighScores, InitialsEntry};
+use crate::input::InputFrame;
+use crate::laser::Lasers;
+use crate::levels::{Campaign, Level};
+use crate::menu::Menu;
+use crate::layout;
+use crate::planets::{Planets, PlanetState};
+use crate::player::{Player, PlayerState};
+use crate::power_up::{Effects, PowerUpKind, PowerUps};
+use crate::projectile::Projectiles;
+use crate::rng::{layout_rng, resume_rng, spawn_rng, GameRng};
+use crate::save::{SaveGame, SAVE_VERSION};
+use crate::timestep::{TICK_SECONDS, TICKS_PER_SECOND};
+use crate::tuning::Tuning;
+
+#[derive(Clone, Copy, Deserialize, Serialize)]
+pub struct SpriteSize {
+    pub width: u32,
+    pub height: u32
+}
+
+impl SpriteSize {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self { width, height }
+    }
+}
+
+/// Sizes of a single frame of every sprite, used for positioning and collisions.
+#[derive(Clone, Copy)]
+pub struct SpriteSizes {
+    pub black_hole: SpriteSize,
+    pub boss: SpriteSize,
+    /// One for every enemy sprite sheet.
+    pub enemies: [SpriteSize; ENEMY_SHEETS],
+    pub laser: SpriteSize,
+    pub planet: SpriteSize,
+    pub player: SpriteSize,
+    /// One frame of the power-up sprite sheet.
+    pub power_up: SpriteSize,
+    pub projectile: SpriteSize
+}
+
+impl Default for SpriteSizes {
+    /// The sizes of the bundled assets.
+    fn default() -> Self {
+        Self {
+            black_hole: SpriteSize::new(64, 64),
+            boss: SpriteSize::new(128, 128),
+            enemies: [SpriteSize::new(64, 64); ENEMY_SHEETS],
+            laser: SpriteSize::new(32, 32),
+            planet: SpriteSize::new(64, 64),
+            player: SpriteSize::new(64, 64),
+            power_up: SpriteSize::new(32, 32),
+            projectile: SpriteSize::new(16, 16)
+        }
+    }
+}
+
+/// The pause menu's items, in order.
+pub const PAUSE_MENU: [&str; 4] = ["Resume", "Restart Level", "Settings", "Quit to Title"];
+const RESUME: usize = 0;
+const RESTART_LEVEL: usize = 1;
+const SETTINGS: usize = 2;
+const QUIT_TO_TITLE: usize = 3;
+
+/// Something the world needs from whoever runs it, collected with `World::take_events`.
+pub enum WorldEvent {
+    /// Settings was chosen from the pause menu.
+    OpenSettings,
+    /// The player quit to the title screen, leaving this game to be continued later.
+    QuitToTitle(Box<SaveGame>)
+}
+
+fn update_score(score: u32, high_score: u32, increment: u32) -> (u32, u32) {
+    let new_score = score + increment;
+    let new_high_score = high_score.max(new_score);
+    (new_score, new_high_score)
+}
+
+/// The whole game simulation. It needs no window or graphics context, so it can be built
+/// and stepped anywhere; a renderer only reads it through the `get_*` views.
+pub struct World {
+    arena: Arena,
+    black_holes: BlackHoles,
+    campaign: Campaign,
+    clock: ManualClock,
+    enemies: Enemies,
+    date: String,
+    events: Vec<WorldEvent>,
+    game: Game,
+    high_scores: HighScores,
+    initials: Option<InitialsEntry>,
+    lasers: Lasers,
+    layout_rng: GameRng,
+    planets: Planets,
+    pause_menu: Option<Menu>,
+    player: Player,
+    power_ups: PowerUps,
+    projectiles: Projectiles,
+    seed: u64,
+    spawn_rng: GameRng,
+    tick: u64,
+    /// The tuning as loaded, before the level's enemy rules are applied.
+    tuning: Tuning
+}
+
+impl World {
+    /// Creates a world whose levels and spawns are all derived from `seed`.
+    pub fn new(screen_width: f64, screen_height: f64, sizes: SpriteSizes, tuning: Tuning, seed: u64) -> Self {
+        let player = Player::new(screen_width, screen_height, sizes.player.width, sizes.player.height);
+        let game = Game{
+            black_hole_count: tuning.starting_black_holes,
+            black_holes: Vec::new(),
+            difficulty: Difficulty::default(),
+            effects: Effects::default(),
+            enemy_kinds: EnemyKind::for_level(1),
+            high_score: 0,
+            input: InputFrame::default(),
+            level: 1,
+            level_score: 0,
+            lives: tuning.starting_lives,
+            obstacles: Vec::new(),
+            player: player.get_sprite().get_position(),
+            score: 0,
+            screen_height,
+            screen_width,
+            state: GameState::Starting,
+            timer_start: Duration::ZERO,
+            towed_planet: None,
+            tuning: tuning.clone()
+        };
+        let level_rng = layout_rng(seed, game.black_hole_count);
+
+        Self {
+            arena: Arena::new(sizes.boss, BOSS_FRAMES),
+            black_holes: BlackHoles::new(sizes.black_hole.width, sizes.black_hole.height),
+            campaign: Campaign::default(),
+            clock: ManualClock::new(),
+            date: String::new(),
+            enemies: Enemies::new(sizes.enemies, ENEMY_FRAMES),
+            events: Vec::new(),
+            game,
+            high_scores: HighScores::default(),
+            initials: None,
+            lasers: Lasers::new(screen_width, screen_height, sizes.laser.width, sizes.laser.height),
+            layout_rng: level_rng,
+            planets: Planets::new(sizes.planet.width, sizes.planet.height),
+            pause_menu: None,
+            player,
+            power_ups: PowerUps::new(sizes.power_up.width, sizes.power_up.height),
+            projectiles: Projectiles::new(sizes.projectile.width, sizes.projectile.height),
+            seed,
+            spawn_rng: spawn_rng(seed),
+            tick: 0,
+            tuning
+        }
+    }
+
+    /// Continues a saved game, with today's `tuning`. A game saved while playing comes back paused.
+    pub fn from_save(save: SaveGame, tuning: Tuning) -> Self {
+        let game = save.game;
+        let level_rng = layout_rng(save.seed, game.black_hole_count);
+        let mut clock = ManualClock::new();
+        clock.set(save.time);
+        let pause_menu = (game.state == GameState::Paused).then(|| Menu::new(&PAUSE_MENU));
+
+        let mut world = Self {
+            arena: save.arena,
+            black_holes: save.black_holes,
+            campaign: Campaign::default(),
+            clock,
+            date: String::new(),
+            enemies: save.enemies,
+            events: Vec::new(),
+            game,
+            high_scores: HighScores::default(),
+            initials: None,
+            lasers: save.lasers,
+            layout_rng: level_rng,
+            pause_menu,
+            planets: save.planets,
+            player: save.player,
+            power_ups: save.power_ups,
+            projectiles: save.projectiles,
+            seed: save.seed,
+            // the spawn generator's position can't be saved, so carry on from a stream of its own
+            spawn_rng: resume_rng(save.seed, save.tick),
+            tick: save.tick,
+            tuning
+        };
+        // the tuning and kinds of enemy the level plays with aren't saved, so work them out again
+        world.apply_level_tuning();
+        world
+    }
+
+    /// Everything needed to continue this game later.
+    pub fn to_save(&self) -> SaveGame {
+        let mut game = self.game.clone();
+        if game.state == GameState::Running {
+            game.state = GameState::Paused;
+        }
+        SaveGame {
+            version: SAVE_VERSION,
+            seed: self.seed,
+            tick: self.tick,
+            time: self.clock.now(),
+            game,
+            black_holes: self.black_holes.clone(),
+            arena: self.arena.clone(),
+            enemies: self.enemies.clone(),
+            lasers: self.lasers.clone(),
+            planets: self.planets.clone(),
+            player: self.player.clone(),
+            power_ups: self.power_ups.clone(),
+            projectiles: self.projectiles.clone()
+        }
+    }
+
+    /// Whether there is a game in progress worth saving.
+    pub fn is_in_progress(&self) -> bool {
+        self.game.state != GameState::Starting && self.game.state != GameState::Over
+    }
+
+    pub fn get_arena(&self) -> &Arena {
+        &self.arena
+    }
+
+    pub fn get_black_holes(&self) -> &BlackHoles {
+        &self.black_holes
+    }
+
+    /// Game time, which advances by exactly one tick per `step` except while paused.
+    pub fn get_clock(&self) -> &dyn Clock {
+        &self.clock
+    }
+
+    pub fn get_enemies(&self) -> &Enemies {
+        &self.enemies
+    }
+
+    pub fn get_game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn get_high_scores(&self) -> &HighScores {
+        &self.high_scores
+    }
+
+    /// The initials being entered for a new high score, if the game just ended with one.
+    pub fn get_initials_entry(&self) -> Option<&InitialsEntry> {
+        self.initials.as_ref()
+    }
+
+    pub fn get_lasers(&self) -> &Lasers {
+        &self.lasers
+    }
+
+    pub fn get_power_ups(&self) -> &PowerUps {
+        &self.power_ups
+    }
+
+    pub fn get_projectiles(&self) -> &Projectiles {
+        &self.projectiles
+    }
+
+    pub fn get_planets(&self) -> &Planets {
+        &self.planets
+    }
+
+    /// The pause menu, while the game is paused.
+    pub fn get_pause_menu(&self) -> Option<&Menu> {
+        self.pause_menu.as_ref()
+    }
+
+    pub fn get_player(&self) -> &Player {
+        &self.player
+    }
+
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The number of ticks stepped so far.
+    pub fn get_tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// The hand-authored level being played, or `None` once the campaign has run out and levels
+    /// are generated.
+    pub fn get_authored_level(&self) -> Option<&Level> {
+        self.campaign.get_level(self.game.get_level())
+    }
+
+    /// How long this life has left to finish the level, on levels with a time limit.
+    pub fn get_time_left(&self) -> Option<Duration> {
+        let limit = self.get_authored_level()?.get_time_limit()?;
+        Some(limit.saturating_sub(self.clock.since(self.game.timer_start)))
+    }
+
+    /// Everything the world has asked for since the last call.
+    pub fn take_events(&mut self) -> Vec<WorldEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Sets the table new high scores go into, which also gives the high score to beat.
+    pub fn set_high_scores(&mut self, high_scores: HighScores) {
+        self.game.high_score = self.game.high_score.max(high_scores.get_best());
+        self.high_scores = high_scores;
+    }
+
+    /// Puts the new high score in the table with the initials entered so far, such as when the
+    /// game is closed part way through entering them.
+    pub fn finish_initials(&mut self) {
+        if let Some(entry) = self.initials.take() {
+            self.high_scores.insert(HighScore {
+                initials: entry.get_initials(),
+                score: self.game.score,
+                level: self.game.get_level(),
+                date: self.date.clone()
+            });
+        }
+    }
+
+    /// The date new high scores are stamped with.
+    pub fn set_date(&mut self, date: String) {
+        self.date = date;
+    }
+
+    /// Swaps in new balance values. Starting lives and black holes apply from the next game.
+    pub fn set_tuning(&mut self, tuning: Tuning) {
+        self.tuning = tuning;
+        self.apply_level_tuning();
+    }
+
+    /// Sets the authored levels played before the generated ones.
+    pub fn set_campaign(&mut self, campaign: Campaign) {
+        self.campaign = campaign;
+        self.apply_level_tuning();
+    }
+
+    // the level's enemy rules on top of the loaded tuning, made as hard as the level and
+    // difficulty call for, and the kinds of enemy the level spawns
+    fn apply_level_tuning(&mut self) {
+        let level = self.game.get_level();
+        let (tuning, kinds) = match self.get_authored_level() {
+            Some(authored) => (authored.enemies.apply(&self.tuning), authored.enemies.kinds.clone()),
+            None => (self.tuning.clone(), None)
+        };
+        self.game.tuning = self.game.difficulty.apply(&tuning, level);
+        self.game.enemy_kinds = kinds.unwrap_or_else(|| EnemyKind::for_level(level));
+    }
+
+    /// Advances the simulation by one fixed tick of `TICK_SECONDS`.
+    pub fn step(&mut self, input: InputFrame) {
+        self.tick += 1;
+        // the clock stands still while paused, so every timer picks up where it left off
+        if self.game.state == GameState::Running && input.pause {
+            self.game.state = GameState::Paused;
+            self.pause_menu = Some(Menu::new(&PAUSE_MENU));
+            return;
+        }
+        if self.game.state == GameState::Paused {
+            self.step_paused(input);
+            return;
+        }
+        self.clock.advance(Duration::from_secs(1) / TICKS_PER_SECOND);
+
+        // a game keeps the difficulty it started on
+        if let Some(difficulty) = input.difficulty.filter(|_| !self.is_in_progress()) {
+            self.game.difficulty = difficulty;
+            self.apply_level_tuning();
+        }
+
+        let mut input = input;
+        let now = self.get_tick_time();
+        if let Some(entry) = self.initials.as_mut() {
+            entry.update(&input, now);
+            if entry.is_finished() {
+                self.finish_initials();
+            }
+            // confirming initials doesn't start a new game as well
+            input.confirm = false;
+        }
+        let game = &mut self.game;
+        game.input = input;
+
+        // the death animation can't be skipped, or the player could keep playing with no lives left
+        let waiting = matches!(game.state, GameState::Starting | GameState::LevelComplete | GameState::Dead | GameState::Over);
+        if input.confirm && waiting {
+            if game.state == GameState::Over {
+                game.lives = game.tuning.starting_lives;
+                game.black_hole_count = game.tuning.starting_black_holes;
+                game.level = 1;
+                game.score = 0;
+            } else if game.state == GameState::LevelComplete {
+                game.black_hole_count += 1;
+                game.level += 1;
+            }
+
+            if game.state != GameState::Dead {
+                // a new level, so lay it out from its own stream of the seed
+                if game.state != GameState::LevelComplete {
+                    self.spawn_rng = spawn_rng(self.seed);
+                }
+                self.layout_rng = layout_rng(self.seed, game.black_hole_count);
+                game.level_score = game.score;
+            }
+            game.timer_start = self.clock.now();
+
+            self.player.reset();
+            for (_, planet) in self.planets.get_planets().iter_mut() {
+                if planet.get_state() == PlanetState::Towed {
+                    planet.not_towed();
+                }
+            }
+            game.state = GameState::Running;
+            self.apply_level_tuning();
+        }
+
+        let game = &mut self.game;
+        match game.state {
+            GameState::Starting | GameState::Over | GameState::LevelComplete => {
+                self.black_holes.reset();
+                self.arena.reset();
+                self.enemies.reset();
+                self.lasers.reset();
+                self.projectiles.reset();
+                self.planets.reset();
+                self.power_ups.reset();
+                game.effects.clear();
+                self.player.reset();
+            }
+            GameState::Dying => {
+                self.player.update(game, TICK_SECONDS);
+                if self.player.get_state() == PlayerState::Dead {
+                    if game.lives == 0 {
+                        game.state = GameState::Over;
+                        if self.high_scores.qualifies(game.score) {
+                            self.initials = Some(InitialsEntry::new());
+                        }
+                    } else {
+                        game.state = GameState::Dead;
+                    }
+                }
+            }
+            GameState::Dead => {
+                self.enemies.reset();
+                self.lasers.reset();
+                self.projectiles.reset();
+                game.effects.clear();
+            }
+            GameState::Running => self.step_running(),
+            GameState::Paused => {}
+        }
+    }
+
+    // real time, for menus, which keeps going while the game is paused
+    fn get_tick_time(&self) -> Duration {
+        Duration::from_secs(self.tick) / TICKS_PER_SECOND
+    }
+
+    fn step_paused(&mut self, input: InputFrame) {
+        let now = self.get_tick_time();
+        let chosen = match self.pause_menu.as_mut() {
+            Some(menu) => menu.update(&input, now),
+            None => Some(RESUME)
+        };
+        let chosen = if input.pause { Some(RESUME) } else { chosen };
+        match chosen {
+            Some(RESUME) => {
+                self.game.state = GameState::Running;
+                self.pause_menu = None;
+            }
+            Some(RESTART_LEVEL) => {
+                self.restart_level();
+                self.pause_menu = None;
+            }
+            Some(SETTINGS) => self.events.push(WorldEvent::OpenSettings),
+            Some(QUIT_TO_TITLE) => {
+                self.events.push(WorldEvent::QuitToTitle(Box::new(self.to_save())));
+                self.quit_to_title();
+            }
+            _ => {}
+        }
+    }
+
+    // lays the level out again from the same stream, and takes back the points scored on it
+    fn restart_level(&mut self) {
+        let game = &mut self.game;
+        game.score = game.level_score;
+        game.high_score = self.high_scores.get_best().max(game.score);
+        self.layout_rng = layout_rng(self.seed, game.black_hole_count);
+        self.black_holes.reset();
+        self.arena.reset();
+        self.enemies.reset();
+        self.lasers.reset();
+        self.projectiles.reset();
+        self.planets.reset();
+        self.player.reset();
+        self.power_ups.reset();
+        game.effects.clear();
+        game.player = self.player.get_sprite().get_position();
+        game.timer_start = self.clock.now();
+        game.state = GameState::Running;
+    }
+
+    // the abandoned game has been saved, so the next one starts from scratch
+    fn quit_to_title(&mut self) {
+        let game = &mut self.game;
+        game.lives = game.tuning.starting_lives;
+        game.black_hole_count = game.tuning.starting_black_holes;
+        game.level = 1;
+        game.score = 0;
+        game.level_score = 0;
+        game.high_score = self.high_scores.get_best();
+        game.state = GameState::Starting;
+        self.pause_menu = None;
+        self.black_holes.reset();
+        self.arena.reset();
+        self.enemies.reset();
+        self.lasers.reset();
+        self.projectiles.reset();
+        self.planets.reset();
+        self.player.reset();
+        self.power_ups.reset();
+        game.effects.clear();
+        self.apply_level_tuning();
+    }
+
+    // the authored layout while the campaign lasts, then a generated one
+    fn lay_out_level(&mut self) {
+        let black_hole = self.black_holes.get_sprite_size();
+        let planet = self.planets.get_sprite_size();
+        let playfield = (self.game.screen_width, self.game.screen_height);
+        let layout = match self.campaign.get_level(self.game.get_level()) {
+            Some(level) => level.to_layout(black_hole, planet, playfield),
+            None => layout::generate(
+                &mut self.layout_rng, self.game.black_hole_count, black_hole, planet,
+                playfield, self.player.get_sprite().get_position()
+            )
+        };
+        self.black_holes.place(&layout.black_holes, self.clock.now());
+        self.planets.place(&layout.planets);
+        if self.game.is_boss_level() {
+            self.black_holes.seal();
+            self.arena.place(self.game.screen_width, self.game.tuning.boss_hit_points, self.clock.now());
+        }
+    }
+
+    fn step_running(&mut self) {
+        if self.black_holes.iter().next().is_none() {
+            self.lay_out_level();
+        }
+        let out_of_time = self.get_time_left() == Some(Duration::ZERO);
+
+        let game = &mut self.game;
+        let player = &mut self.player;
+        game.effects.update(Duration::from_secs_f64(TICK_SECONDS));
+        game.player = player.get_sprite().get_position();
+        game.black_holes = self.black_holes.get_black_holes().iter()
+            .filter(|h|h.get_state() == BlackHoleState::Open)
+            .map(|h|h.get_sprite().get_position()).collect();
+        game.obstacles = self.planets.iter()
+            .filter(|p| p.get_state() != PlanetState::Towed)
+            .map(|p| p.get_sprite().get_position()).collect();
+        game.towed_planet = self.planets.iter()
+            .find(|p| p.get_state() == PlanetState::Towed)
+            .map(|p| p.get_sprite().get_position());
+
+        player.update(game, TICK_SECONDS);
+        self.black_holes.update(&self.clock);
+        self.planets.update(game);
+        let mut shots = self.enemies.update(game, &self.clock, &mut self.spawn_rng, TICK_SECONDS);
+        shots.extend(self.arena.update(game, &self.clock, TICK_SECONDS));
+        for (from, to) in shots {
+            self.projectiles.fire(from, to, game.tuning.projectile_speed, self.clock.now());
+        }
+        self.projectiles.update(game, &self.clock, TICK_SECONDS);
+        self.lasers.update(game, &self.clock, TICK_SECONDS);
+        self.power_ups.update(game, &self.clock, &mut self.spawn_rng);
+
+        if out_of_time && player.get_state() != PlayerState::Dying {
+            game.lives = game.lives.saturating_sub(1);
+            player.dying();
+            game.state = GameState::Dying;
+        }
+
+        let pr = player.get_sprite().get_position();
+        let shielded = player.is_shielded(game);
+        let mut enemies_to_remove: Vec<Uuid> = vec![];
+        let mut killed: Vec<(f64, f64)> = vec![];
+        let mut lasers_to_remove: Vec<Uuid> = vec![];
+        let mut splitting: Vec<GameSprite> = vec![];
+        for (ei, e) in self.enemies.get_enemies().iter_mut() {
+            if e.get_state() == EnemyState::Dying {
+                continue;
+            }
+
+            if e.get_state() == EnemyState::Dead {
+                enemies_to_remove.push( *ei);
+                continue;
+            }
+
+            let er = e.get_sprite().get_position();
+            if player.get_state() != PlayerState::Dying && er.has_intersection(pr) {
+                // the shield breaks up anything that runs into it
+                if shielded {
+                    e.dying();
+                    (game.score, game.high_score) = update_score(game.score, game.high_score, game.tuning.enemy_points * e.get_kind().get_points());
+                    killed.push(e.get_center());
+                    continue;
+                }
+                enemies_to_remove.push( *ei);
+                game.lives = game.lives.saturating_sub(1);
+                player.dying();
+                game.state = GameState::Dying;
+                continue;
+            }
+
+            for (li, l) in self.lasers.get_lasers().iter() {
+                let lr = l.get_sprite().get_position();
+                if lr.has_intersection(er) {
+                    lasers_to_remove.push( * li);
+                    if e.hit() {
+                        let kind = e.get_kind();
+                        (game.score, game.high_score) = update_score(game.score, game.high_score, game.tuning.enemy_points * kind.get_points());
+                        if kind.splits() {
+                            splitting.push(e.get_sprite());
+                        }
+                        killed.push(e.get_center());
+                        break;
+                    }
+                }
+            }
+        }
+
+        // beating the boss opens the black holes, and touching it is as deadly as any enemy
+        if let Some(boss) = self.arena.get_boss_mut().filter(|b| b.get_state() == BossState::Alive) {
+            let br = boss.get_sprite().get_position();
+            if player.get_state() != PlayerState::Dying && !shielded && br.has_intersection(pr) {
+                game.lives = game.lives.saturating_sub(1);
+                player.dying();
+                game.state = GameState::Dying;
+            }
+            for (li, l) in self.lasers.get_lasers().iter() {
+                if !lasers_to_remove.contains(li) && l.get_sprite().get_position().has_intersection(br) {
+                    lasers_to_remove.push(*li);
+                    if boss.hit() {
+                        (game.score, game.high_score) = update_score(game.score, game.high_score, game.tuning.boss_points);
+                        game.lives += 1;
+                        self.black_holes.unseal();
+                        break;
+                    }
+                }
+            }
+        }
+
+        // a shot kills the player just as touching an enemy does, and knocks loose a towed planet
+        // it hits without hitting the ship, which the planet sits right on top of
+        let mut projectiles_to_remove: Vec<Uuid> = vec![];
+        for projectile in self.projectiles.iter() {
+            let r = projectile.get_sprite().get_position();
+            if player.get_state() != PlayerState::Dying && r.has_intersection(pr) {
+                projectiles_to_remove.push(projectile.get_sprite().get_id());
+                if !shielded {
+                    game.lives = game.lives.saturating_sub(1);
+                    player.dying();
+                    game.state = GameState::Dying;
+                }
+            } else if let Some(planet) = self.planets.get_planets().values_mut()
+                .find(|p| p.get_state() == PlanetState::Towed && p.get_sprite().get_position().has_intersection(r)) {
+                planet.knocked_loose(projectile.get_direction(), game.screen_width, game.screen_height);
+                player.not_towing();
+                projectiles_to_remove.push(projectile.get_sprite().get_id());
+            }
+        }
+        for p in projectiles_to_remove.iter() {
+            self.projectiles.remove(p);
+        }
+
+        // destroyed enemies now and then leave a power-up behind
+        for position in killed {
+            if self.spawn_rng.gen::<f64>() < game.tuning.power_up_drop_chance {
+                self.power_ups.add(PowerUpKind::random(&mut self.spawn_rng), position, self.clock.now());
+            }
+        }
+
+        // power-ups take effect as soon as they're picked up; the timed ones run down from then
+        if player.get_state() != PlayerState::Dying {
+            for kind in self.power_ups.collect(pr) {
+                game.effects.start(kind, kind.get_duration(&game.tuning));
+                match kind {
+                    PowerUpKind::SmartBomb => {
+                        for e in self.enemies.get_enemies().values_mut().filter(|e| e.get_state() == EnemyState::Alive) {
+                            e.dying();
+                            (game.score, game.high_score) = update_score(game.score, game.high_score, game.tuning.enemy_points * e.get_kind().get_points());
+                        }
+                        self.projectiles.reset();
+                    }
+                    PowerUpKind::ExtraLife => game.lives += 1,
+                    _ => {}
+                }
+            }
+        }
+
+        if player.get_state() == PlayerState::NotTowing {
+            for (_, planet) in self.planets.get_planets().iter_mut() {
+                if (planet.get_state() == PlanetState::NotTowed) && (planet.get_sprite().get_position().has_intersection(pr)) {
+                    player.towing();
+                    planet.towed();
+                    break;
+                }
+            }
+        }
+
+        for (_, planet) in self.planets.get_planets().iter_mut() {
+            if planet.get_state() == PlanetState::Towed {
+                for black_hole in self.black_holes.get_black_holes().iter_mut() {
+                    if (black_hole.get_state() == BlackHoleState::Open) && (black_hole.get_sprite().get_position().has_intersection(planet.get_sprite().get_position())) {
+                        black_hole.covered();
+                        planet.in_place(black_hole.get_sprite().get_position());
+                        player.not_towing();
+                        (game.score, game.high_score) = update_score(game.score, game.high_score, game.tuning.black_hole_points);
+                        break;
+                    }
+                }
+            }
+        }
+
+        for e in enemies_to_remove.iter() {
+            self.enemies.remove(e);
+        }
+
+        for splitter in splitting.iter() {
+            self.enemies.split(splitter, self.clock.now(), &mut self.spawn_rng);
+        }
+
+        for l in lasers_to_remove.iter() {
+            self.lasers.remove(l);
+        }
+
+        if !self.black_holes.get_black_holes().is_empty() {
+            let covered = self.black_holes.get_black_holes().iter().all(|h| h.get_state() == BlackHoleState::Covered);
+            if covered {
+                game.state = GameState::LevelComplete;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn started_world() -> World {
+        let mut world = World::new(1600.0, 900.0, SpriteSizes::default(), Tuning::default(), 42);
+        world.step(InputFrame { confirm: true, ..InputFrame::default() });
+        world
+    }
+
+    fn step_for(world: &mut World, duration: Duration) {
+        for _ in 0..(duration.as_secs_f64() * TICKS_PER_SECOND as f64).round() as u64 {
+            world.step(InputFrame::default());
+        }
+    }
+
+    #[test]
+    fn every_step_moves_the_clock_on_a_tick() {
+        let mut world = started_world();
+        step_for(&mut world, Duration::from_secs(2));
+        assert_eq!(world.get_tick(), 1 + 2 * TICKS_PER_SECOND as u64);
+        assert_eq!(world.get_clock().now(), Duration::from_secs(1) / TICKS_PER_SECOND * (1 + 2 * TICKS_PER_SECOND));
+        assert_eq!(world.get_game().state, GameState::Running);
+        assert_eq!(world.get_black_holes().iter().count(), world.get_game().tuning.starting_black_holes as usize);
+    }
+
+    #[test]
+    fn enemies_wait_out_the_spawn_delay() {
+        let mut world = started_world();
+        let delay = world.get_game().tuning.get_enemy_spawn_delay();
+        step_for(&mut world, delay / 2);
+        assert_eq!(world.get_enemies().iter().count(), 0);
+        step_for(&mut world, delay);
+        assert!(world.get_enemies().iter().count() > 0);
+    }
+
+    #[test]
+    fn pausing_stops_the_clock() {
+        let mut world = started_world();
+        step_for(&mut world, Duration::from_secs(1));
+        world.step(InputFrame { pause: true, ..InputFrame::default() });
+        let paused_at = world.get_clock().now();
+        step_for(&mut world, Duration::from_secs(1));
+        assert_eq!(world.get_game().state, GameState::Paused);
+        assert_eq!(world.get_clock().now(), paused_at);
+    }
+
+    #[test]
+    fn the_same_seed_lays_out_the_same_level() {
+        let positions = |world: &World| -> Vec<crate::rect::Rect> {
+            world.get_black_holes().iter().map(|h| h.get_sprite().get_position()).collect()
+        };
+        let (mut first, mut second) = (started_world(), started_world());
+        first.step(InputFrame::default());
+        second.step(InputFrame::default());
+        assert_eq!(positions(&first), positions(&second));
+    }
+
+    #[test]
+    fn a_shot_kills_a_ship_towing_a_planet() {
+        let mut world = started_world();
+        world.step(InputFrame::default());
+        world.planets.get_planets().values_mut().next().unwrap().towed();
+        world.player.towing();
+        let ship = world.player.get_sprite().get_position();
+        let middle = (ship.x as f64 + ship.w as f64 / 2.0, ship.y as f64 + ship.h as f64 / 2.0);
+        world.projectiles.fire(middle, (middle.0 + 1.0, middle.1), 1.0, world.clock.now());
+        let lives = world.get_game().lives;
+
+        world.step(InputFrame::default());
+        assert_eq!(world.get_game().lives, lives - 1);
+        assert_eq!(world.get_game().state, GameState::Dying);
+        assert_eq!(world.get_projectiles().iter().count(), 0);
+    }
+
+    #[test]
+    fn changing_the_tuning_keeps_the_level() {
+        let mut world = started_world();
+        world.step(InputFrame::default());
+        let tuning = Tuning { starting_black_holes: 1, ..Tuning::default() };
+        assert_ne!(tuning.starting_black_holes, world.get_game().black_hole_count);
+        world.set_tuning(tuning);
+        assert_eq!(world.get_game().get_level(), 1);
+    }
+
+    #[test]
+    fn unfinished_initials_still_keep_the_high_score() {
+        let mut world = started_world();
+        world.game.score = 500;
+        world.initials = Some(InitialsEntry::new());
+        world.finish_initials();
+        assert!(world.get_initials_entry().is_none());
+        assert_eq!(world.get_high_scores().get_best(), 500);
+    }
+}